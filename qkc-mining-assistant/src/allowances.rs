@@ -1,17 +1,28 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use crossbeam_channel::Sender;
 use qkc_web3_rs::{
-    qkc::{MinorBlockData, RootBlockData},
-    types::QkcAddress,
-    QkcWeb3,
+    types::{to_checksum, QkcAddress, H160},
+    Error, QkcWeb3,
+};
+use std::{
+    fmt,
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use std::{process::Stdio, sync::Arc};
 use tokio::{
     process::{Child, Command},
     task::JoinHandle,
+    time::Instant,
 };
 
-use crate::{IniParameters, MinerIni};
+use crate::{window::SharedWindow, IniParameters, MinerIni};
+
+/// Difficulty divider for staking miners on shards, used if the node does not report PoSW info
+static POSW_DIFFICULTY_DIVIDER: u128 = 20;
+
+/// Delay before polling again after the node could not be reached
+static RETRY_DELAY: Duration = Duration::from_secs(5);
 
 static ROOT_ALLOWANCE: u128 = 681_500e18 as u128;
 
@@ -28,18 +39,20 @@ static ALLOWANCES: [u128; 8] = [
 
 #[derive(Debug, Clone)]
 pub struct AllowanceThread {
-    pub config: Arc<MinerIni>,
     pub sender: Sender<AllowanceInfo>,
     pub balance: u128,
     pub address: Arc<QkcAddress>,
+    /// Owner of the root chain stakes, the coinbase of the root blocks mined with them
+    pub stake_owner: Arc<QkcAddress>,
+    pub signer: Option<H160>,
     pub web3: Arc<QkcWeb3>,
     pub config_file: Arc<IniParameters>,
+    pub window: SharedWindow,
 }
 
 #[derive(Debug, Clone)]
 pub struct AllowanceInfo {
     pub config: Arc<IniParameters>,
-    pub config_ini: Arc<MinerIni>,
     pub address: Arc<QkcAddress>,
     pub difficulty: u128,
 
@@ -81,6 +94,8 @@ impl AllowanceInfo {
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit())
                 .stdin(Stdio::piped())
+                // miners of other configs are stopped when the assistant exits on an error
+                .kill_on_drop(true)
                 .spawn()?;
 
             *child = Some(child_inner);
@@ -91,6 +106,7 @@ impl AllowanceInfo {
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit())
                 .stdin(Stdio::piped())
+                .kill_on_drop(true)
                 .spawn()?;
 
             *child = Some(child_inner);
@@ -99,38 +115,70 @@ impl AllowanceInfo {
     }
 }
 
+/// The signer configured for root chain stakes differs from the one set in the staking contract
+#[derive(Debug)]
+pub struct SignerMismatch {
+    pub stake_owner: QkcAddress,
+    pub signer: H160,
+    pub contract_signer: H160,
+}
+
+impl fmt::Display for SignerMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Signer {} of {:#} does not match signer {} set in the staking contract, its root blocks would not get PoSW",
+            to_checksum(&self.signer),
+            self.stake_owner,
+            to_checksum(&self.contract_signer)
+        )
+    }
+}
+
+impl std::error::Error for SignerMismatch {}
+
 impl AllowanceThread {
-    pub fn new(
+    pub fn spawn(
         config: Arc<MinerIni>,
         sender: Sender<AllowanceInfo>,
         web3: Arc<QkcWeb3>,
         config_file: Arc<IniParameters>,
+        window: SharedWindow,
     ) -> JoinHandle<Result<()>> {
         tokio::spawn(async move {
+            let address = Arc::new(config.wallet);
+            let stake_owner = Arc::new(config_file.stake_owner.unwrap_or(config.wallet));
+            let signer = config_file.signer;
+
             let mut config = AllowanceThread {
                 balance: 0,
-                address: Arc::new(QkcAddress::new_full(&config.wallet)?),
+                address,
+                stake_owner,
+                signer,
                 web3,
-                config,
                 sender,
                 config_file,
+                window,
             };
 
             loop {
                 let time = tokio::time::Instant::now() + std::time::Duration::from_secs(60);
                 if config.config_file.root_chain {
                     match config.root_allowances_left().await {
+                        Err(e) if e.is::<SignerMismatch>() => {
+                            error!("{e}, stopped polling {}", config.address);
+                            return Err(e);
+                        }
                         Ok((used, allowances)) => {
                             info!(
                                 "Address {}: {} used / {} allowances (in recent 256 blocks)",
-                                config.address.to_string(),
+                                config.address,
                                 used,
                                 allowances
                             );
 
                             config.sender.send(AllowanceInfo {
                                 config: config.config_file.clone(),
-                                config_ini: config.config.clone(),
                                 difficulty: 0,
                                 used,
                                 allowances,
@@ -138,7 +186,7 @@ impl AllowanceThread {
                             })?;
                         }
                         Err(e) => {
-                            warn!("Error: {e:?}");
+                            tokio::time::sleep_until(config.retry_at(&e, time)).await;
                             continue;
                         }
                     };
@@ -147,7 +195,7 @@ impl AllowanceThread {
                         Ok((used, allowances, difficulty)) => {
                             info!(
                                 "Address {}: ({}/{} in recent 256 blocks) difficulty: {:.4}G",
-                                config.address.to_string(),
+                                config.address,
                                 used,
                                 allowances,
                                 difficulty as f64 / 1e9
@@ -155,7 +203,6 @@ impl AllowanceThread {
 
                             config.sender.send(AllowanceInfo {
                                 config: config.config_file.clone(),
-                                config_ini: config.config.clone(),
                                 difficulty,
                                 used,
                                 allowances,
@@ -163,7 +210,7 @@ impl AllowanceThread {
                             })?;
                         }
                         Err(e) => {
-                            warn!("Error: {e:?}");
+                            tokio::time::sleep_until(config.retry_at(&e, time)).await;
                             continue;
                         }
                     };
@@ -173,59 +220,81 @@ impl AllowanceThread {
         })
     }
 
-    async fn root_allowances_left(&mut self) -> Result<(u32, u32)> {
-        use qkc_web3_rs::qkc::Block;
-        if self.balance == 0 {
-            self.balance = self.web3.qkc().get_root_posw_stake(&self.address).await?;
+    /// Logs a failed poll and decides when to poll again: an unreachable node is retried after
+    /// [`RETRY_DELAY`], any other failure (rejected request, malformed or empty result) waits for the next regular poll
+    fn retry_at(&self, e: &anyhow::Error, next_poll: Instant) -> Instant {
+        match e.downcast_ref::<qkc_web3_rs::Error>() {
+            Some(Error::Rpc(rpc)) => {
+                warn!("Node rejected request for {:#}: {rpc}", self.address);
+                next_poll
+            }
+            Some(Error::HttpStatus { status, .. }) => {
+                warn!("Node returned http status {status} for {:#}", self.address);
+                next_poll
+            }
+            Some(e @ (Error::Transport(_) | Error::Timeout | Error::NoEndpoint)) => {
+                warn!("Node unreachable for {:#}: {e}", self.address);
+                Instant::now() + RETRY_DELAY
+            }
+            _ => {
+                warn!("Error: {e:?}");
+                next_poll
+            }
         }
+    }
 
-        let allowances = self.balance / ROOT_ALLOWANCE;
-
-        let mut blocks = vec![
-            self.web3
-                .qkc()
-                .get_root_block_by_height(Block::Latest)
-                .await?,
-        ];
-        let id = u64::from_str_radix(&blocks[0].height[2..], 16)?;
-
-        let block_ids: Vec<String> = (id - 255..id)
-            .map(|i| format!("0x{}", hex::encode(&i.to_be_bytes())))
-            .collect();
-
-        let mut handles = Vec::new();
-
-        for chunk in block_ids.chunks(10) {
-            let chunk = chunk.to_vec();
-            let web3 = self.web3.clone();
-
-            let handle: JoinHandle<Result<Vec<RootBlockData>>> = tokio::spawn(async move {
-                let mut blocks = Vec::new();
-
-                for id in chunk {
-                    blocks.push(web3.qkc().get_root_block_by_height(Block::Id(id)).await?);
-                }
-
-                Ok(blocks)
-            });
-
-            handles.push(handle);
+    async fn root_allowances_left(&mut self) -> Result<(u32, u32)> {
+        let stakes = self.web3.qkc().get_root_locked_stakes(&self.stake_owner).await?;
+
+        if stakes.unlocked {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+            if stakes.in_unlock_period(now) {
+                warn!(
+                    "Stakes of {:#} are in the unlock period and do not count towards PoSW, withdrawable in {}h",
+                    self.stake_owner,
+                    (stakes.withdrawable_timestamp - now) / 3600
+                );
+            } else {
+                warn!("Stakes of {:#} are unlocked and can be withdrawn", self.stake_owner);
+            }
         }
 
-        for handle in handles {
-            blocks.extend(handle.await??);
+        // root blocks signed by another key than the contract's signer do not get PoSW
+        if let Some(signer) = self.signer.filter(|signer| *signer != stakes.signer()) {
+            return Err(SignerMismatch {
+                stake_owner: *self.stake_owner,
+                signer,
+                contract_signer: stakes.signer(),
+            }
+            .into());
         }
 
-        let mined: u32 = blocks
-            .into_iter()
-            .map(|x| x.miner.starts_with(&self.address.coinbase()) as u32)
-            .sum();
+        self.balance = stakes
+            .locked_amount()
+            .try_into()
+            .map_err(|_| anyhow!("stakes {} exceed u128", stakes.amount))?;
+
+        let mut window = self.window.lock().await;
+        window.update(&self.web3).await?;
+        let mined = window.mined_by(&self.stake_owner.coinbase);
+        let allowances = window
+            .mineable_by(&self.stake_owner.coinbase)
+            .unwrap_or((self.balance / ROOT_ALLOWANCE) as u32);
 
-        Ok((mined, allowances as u32))
+        Ok((mined, allowances))
     }
 
     async fn allowances_left(&mut self) -> Result<(u32, u32, u128)> {
-        use qkc_web3_rs::qkc::Block;
+        let mut window = self.window.lock().await;
+        window.update(&self.web3).await?;
+        let difficulty = window.latest().map(|b| b.difficulty).unwrap_or_default()
+            / window.posw_divider().unwrap_or(POSW_DIFFICULTY_DIVIDER);
+
+        if let Some(posw) = self.web3.qkc().posw_info(&self.address).await? {
+            return Ok((posw.mined_blocks as u32, posw.mineable_blocks as u32, difficulty));
+        }
+
         if self.balance == 0 {
             let balance_r = self.web3.qkc().get_account_data(&self.address).await?;
             let balance_r = balance_r
@@ -235,58 +304,68 @@ impl AllowanceThread {
                 .find(|b| b.token_str == "QKC");
 
             if let Some(b) = balance_r {
-                self.balance = u128::from_str_radix(&b.balance[2..], 16)?;
+                self.balance = b.balance.try_into().map_err(|_| anyhow!("balance {} exceeds u128", b.balance))?;
             }
         }
 
-        let allowances = self.balance / ALLOWANCES[self.address.chain_id() as usize];
-
-        let mut blocks = vec![
-            self.web3
-                .qkc()
-                .get_minor_block_by_height(self.address.full_shard_key(), Block::Latest)
-                .await?,
-        ];
-        let difficulty = u128::from_str_radix(&blocks[0].difficulty[2..], 16)?/20;
-        let id = u64::from_str_radix(&blocks[0].height[2..], 16)?;
-
-        let block_ids: Vec<String> = (id - 255..id)
-            .map(|i| format!("0x{}", hex::encode(&i.to_be_bytes())))
-            .collect();
-
-        let mut handles = Vec::new();
-
-        for chunk in block_ids.chunks(10) {
-            let chunk = chunk.to_vec();
-            let web3 = self.web3.clone();
-            let address = self.address.clone();
-
-            let handle: JoinHandle<Result<Vec<MinorBlockData>>> = tokio::spawn(async move {
-                let mut blocks = Vec::new();
-
-                for id in chunk {
-                    blocks.push(
-                        web3.qkc()
-                            .get_minor_block_by_height(address.full_shard_key(), Block::Id(id))
-                            .await?,
-                    );
-                }
-
-                Ok(blocks)
-            });
+        // chain 0 has no allowance and chains above 7 are unknown, the count is skipped until the node reports PoSW info
+        let allowances = ALLOWANCES
+            .get(self.address.chain_id() as usize)
+            .copied()
+            .filter(|allowance| *allowance != 0)
+            .and_then(|allowance| self.balance.checked_div(allowance))
+            .ok_or_else(|| anyhow!("no PoSW allowance known for chain {} of {:#}", self.address.chain_id(), self.address))?;
+        let mined = window.mined_by(&self.address.coinbase);
 
-            handles.push(handle);
-        }
+        Ok((mined, allowances as u32, difficulty))
+    }
+}
 
-        for handle in handles {
-            blocks.extend(handle.await??);
-        }
+#[cfg(test)]
+mod test {
+    use qkc_mock_node::{MockChain, MockNode, MockShard};
+
+    use super::*;
+    use crate::window::{BlockWindow, Chain};
+
+    #[tokio::test]
+    async fn fallback_allowances_test() {
+        let miner = "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b";
+        let mut chain = MockChain {
+            root: MockShard::new(1000, 5_000_000, vec![miner.into()]),
+            ..Default::default()
+        };
+        chain.account(miner).balance = 1_000_000e18 as u128;
+        let node = MockNode::start(chain).await.unwrap();
+
+        let (sender, _receiver) = crossbeam_channel::unbounded();
+        let config_file: IniParameters = serde_json::from_str(
+            r#"{"spawn_args": [], "path": "miner.ini", "mine_at_free_allowances_from_max": 0}"#,
+        )
+        .unwrap();
+        let thread = |chain_id| {
+            let address = Arc::new(QkcAddress::new(miner, chain_id, 0).unwrap());
+
+            AllowanceThread {
+                sender: sender.clone(),
+                balance: 0,
+                stake_owner: address.clone(),
+                address,
+                signer: None,
+                web3: Arc::new(QkcWeb3::new(node.url())),
+                config_file: Arc::new(config_file.clone()),
+                window: BlockWindow::shared(Chain::Root),
+            }
+        };
 
-        let mined: u32 = blocks
-            .into_iter()
-            .map(|x| x.miner.starts_with(&self.address.coinbase()) as u32)
-            .sum();
+        // the node reports no PoSW info, allowances are counted from the balance
+        let (_, allowances, _) = thread(1).allowances_left().await.unwrap();
+        assert_eq!(allowances as u128, 1_000_000e18 as u128 / ALLOWANCES[1]);
 
-        Ok((mined, allowances as u32, difficulty))
+        // no allowance on chain 0 and none known above chain 7
+        for chain_id in [0, 9] {
+            let e = thread(chain_id).allowances_left().await.unwrap_err();
+            assert!(e.to_string().starts_with("no PoSW allowance"), "{e}");
+        }
     }
 }
@@ -1,10 +1,16 @@
 use allowances::{AllowanceInfo, AllowanceThread};
-use anyhow::Result;
+use window::{Chain, Windows};
+use anyhow::{ensure, Result};
 use crossbeam_channel::unbounded;
-use qkc_web3_rs::{types::QkcAddress, QkcWeb3};
-use serde::Deserialize;
-use tokio::{process::Child, task::JoinHandle};
+use qkc_web3_rs::{
+    transport::HttpTransport,
+    types::{QkcAddress, H160},
+    QkcWeb3, RetryPolicy,
+};
+use serde::{de::Error, Deserialize, Deserializer};
+use tokio::{process::Child, task::JoinSet};
 mod allowances;
+mod window;
 
 use std::sync::Arc;
 
@@ -17,29 +23,52 @@ async fn main() -> Result<()> {
     env_logger::init();
 
     let configs = Config::load()?;
-    let mut handles = Vec::new();
+    let mut handles = JoinSet::new();
 
     for config in configs {
-        let handle: JoinHandle<Result<()>> = tokio::spawn(async move {
+        handles.spawn(async move {
             let config_inner = config.clone();
-            let web3 = QkcWeb3::new(config.rpc.clone());
+            let config_inis = config
+                .config_files
+                .iter()
+                .map(|config_file| MinerIni::load(&config_file.path).map(Arc::new))
+                .collect::<Result<Vec<_>>>()?;
+
+            // endpoints are also ranked by the first shard mined on
+            let policy = RetryPolicy::default();
+            let mut transport = HttpTransport::new(config.rpc.urls(), policy.timeout);
+            if let Some((_, config_ini)) = config.config_files.iter().zip(&config_inis).find(|(c, _)| !c.root_chain) {
+                transport = transport.with_shard(config_ini.wallet.full_shard_key_u32());
+            }
+            let web3 = QkcWeb3::with_transport(Arc::new(transport), policy);
             let mut handles = Vec::new();
 
             let fallback_config = Arc::new(config_inner.fallback_config.clone());
             let fallback_ini = Arc::new(MinerIni::load(&fallback_config.path)?);
 
             let (sender, receiver) = unbounded();
+            let mut windows = Windows::default();
+            let layout = match config.config_files.iter().all(|c| c.root_chain) {
+                true => None,
+                false => Some(web3.qkc().shard_layout().await?),
+            };
             let len = config.config_files.len();
-            for (index, mut config_file) in config.config_files.into_iter().enumerate() {
+            for (index, (mut config_file, config_ini)) in config.config_files.into_iter().zip(config_inis).enumerate() {
                 config_file.priority = (len - index) as u16;
-                let config_ini = Arc::new(MinerIni::load(&config_file.path)?);
                 let web3 = Arc::new(web3.clone());
 
-                handles.push(AllowanceThread::new(
+                let chain = match &layout {
+                    Some(layout) if !config_file.root_chain => Chain::minor(&config_ini.wallet, layout)?,
+                    _ => Chain::Root,
+                };
+                let window = windows.get(chain);
+
+                handles.push(AllowanceThread::spawn(
                     config_ini,
                     sender.clone(),
                     web3,
                     Arc::new(config_file),
+                    window,
                 ));
             }
 
@@ -50,6 +79,18 @@ async fn main() -> Result<()> {
 
                 loop {
                     tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+
+                    // allowance threads only end on errors they cannot recover from, e.g. a signer mismatch,
+                    // the miner is stopped instead of mining on with allowances that are no longer updated
+                    if let Some(index) = handles.iter().position(|h| h.is_finished()) {
+                        if let Err(e) = handles.swap_remove(index).await? {
+                            if let Some(child_mut) = child.as_mut() {
+                                child_mut.kill().await?;
+                            }
+                            return Err(e);
+                        }
+                    }
+
                     if !receiver.is_empty() {
                         infos = Vec::new();
                         while !receiver.is_empty() {
@@ -62,12 +103,12 @@ async fn main() -> Result<()> {
                         if let Some(mut current_info_ref) = current_info.clone() {
                             let current_info_update = infos
                                 .iter()
-                                .find(|i| i.address.to_string() == current_info_ref.address.to_string());
+                                .find(|i| i.address == current_info_ref.address);
                             let infos: Vec<&AllowanceInfo> = available_infos;
 
                             if let Some(update) = current_info_update {
                                 if !update.continue_mining() {
-                                    info!("Stopping current miner for {}: {} used / {} allowances (in recent 256 blocks)", update.address.to_string(), update.used, update.allowances);
+                                    info!("Stopping current miner for {:#}: {} used / {} allowances (in recent 256 blocks)", update.address, update.used, update.allowances);
                                     if let Some(child_mut) = child.as_mut() {
                                         child_mut.kill().await?;
                                         child = None;
@@ -91,31 +132,30 @@ async fn main() -> Result<()> {
                             if let Some(replacement) = replacement_info {
 
                                 if current_info_ref.difficulty() > replacement.difficulty() || (current_info_ref.difficulty() == replacement.difficulty() && current_info_ref.priority() < replacement.priority()) {
-                                    info!("Replacing current miner for {} ({}/{}) difficulty {:.4}G, with {} ({}/{}) difficulty {:.4}G", current_info_ref.address.to_string(), current_info_ref.used, current_info_ref.allowances, current_info_ref.difficulty() as f64 / 1e9 as f64, replacement.address.to_string(), replacement.used, replacement.allowances, replacement.difficulty() as f64 / 1e9 as f64);
+                                    info!("Replacing current miner for {:#} ({}/{}) difficulty {:.4}G, with {:#} ({}/{}) difficulty {:.4}G", current_info_ref.address, current_info_ref.used, current_info_ref.allowances, current_info_ref.difficulty() as f64 / 1e9, replacement.address, replacement.used, replacement.allowances, replacement.difficulty() as f64 / 1e9);
                                     replacement
                                         .inject_child(&mut child, &config_inner.miner_exe, &config_inner.miner_dir)
                                         .await?;
-                                    current_info = Some(replacement.clone().clone());
+                                    current_info = Some((*replacement).clone());
                                 } else if current_info.is_none() {
                                     info!(
-                                        "Initializing miner for {} ({}/{}) difficulty {:.4}G",
-                                        replacement.address.to_string(),
+                                        "Initializing miner for {:#} ({}/{}) difficulty {:.4}G",
+                                        replacement.address,
                                         replacement.used,
                                         replacement.allowances,
-                                        replacement.difficulty() as f64 / 1e9 as f64
+                                        replacement.difficulty() as f64 / 1e9
                                     );
                                     replacement
                                         .inject_child(&mut child, &config_inner.miner_exe, &config_inner.miner_dir)
                                         .await?;
-                                    current_info = Some(replacement.clone().clone());
+                                    current_info = Some((*replacement).clone());
                                 }
                             } else if current_info.is_none() {
                                 info!("Initializing miner for fallback");
 
                                 let info = AllowanceInfo {
                                     config: fallback_config.clone(),
-                                    config_ini: fallback_ini.clone(),
-                                    address: Arc::new(QkcAddress::new_full(&fallback_ini.wallet)?),
+                                    address: Arc::new(fallback_ini.wallet),
                                     difficulty: 0,
                                     used: 0,
                                     allowances: 0,
@@ -124,13 +164,12 @@ async fn main() -> Result<()> {
                                     .await?;
                                 current_info = Some(info);
                             }
-                        } else if available_infos.len() == 0 && current_info.is_none() {
+                        } else if available_infos.is_empty() && current_info.is_none() {
                             info!("Initializing miner for fallback");
 
                             let info = AllowanceInfo {
                                 config: fallback_config.clone(),
-                                config_ini: fallback_ini.clone(),
-                                address: Arc::new(QkcAddress::new_full(&fallback_ini.wallet)?),
+                                address: Arc::new(fallback_ini.wallet),
                                 difficulty: 0,
                                 used: 0,
                                 allowances: 0,
@@ -150,27 +189,27 @@ async fn main() -> Result<()> {
 
                             if let Some(replacement) = replacement_info {
                                 info!(
-                                    "Initializing miner for {} ({}/{}) difficulty {:.4}G",
-                                    replacement.address.to_string(),
+                                    "Initializing miner for {:#} ({}/{}) difficulty {:.4}G",
+                                    replacement.address,
                                     replacement.used,
                                     replacement.allowances,
-                                    replacement.difficulty() as f64/1e9 as f64
+                                    replacement.difficulty() as f64/1e9
                                 );
                                 replacement
                                     .inject_child(&mut child, &config_inner.miner_exe, &config_inner.miner_dir)
                                     .await?;
-                                current_info = Some(replacement.clone().clone())
+                                current_info = Some((*replacement).clone())
                             }
                         }
                     }
                 }
             }
         });
-        handles.push(handle);
     }
 
-    for handle in handles {
-        handle.await??;
+    // exits with the first error of any config
+    while let Some(result) = handles.join_next().await {
+        result??;
     }
 
     Ok(())
@@ -186,7 +225,7 @@ pub struct IniFile {
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct MinerIni {
-    pub wallet: String,
+    pub wallet: QkcAddress,
 }
 
 impl MinerIni {
@@ -199,13 +238,30 @@ impl MinerIni {
 
 #[derive(Debug, Clone, Deserialize)]
 struct Config {
-    pub rpc: String,
+    pub rpc: Rpc,
     pub miner_dir: String,
     pub miner_exe: String,
     pub fallback_config: IniParameters,
     pub config_files: Vec<IniParameters>,
 }
 
+/// Single rpc url or list of urls to fail over between
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Rpc {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Rpc {
+    pub fn urls(&self) -> Vec<String> {
+        match self {
+            Rpc::Single(url) => vec![url.clone()],
+            Rpc::Multiple(urls) => urls.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct IniParameters {
     pub spawn_args: Vec<String>,
@@ -216,14 +272,69 @@ pub struct IniParameters {
     pub allowances_to_use: Option<u32>,
     pub mine_at_free_allowances_from_max: u32,
 
+    /// Root chain only, address that locked the stakes if it differs from the wallet in the ini
+    #[serde(default)]
+    pub stake_owner: Option<QkcAddress>,
+    /// Root chain only, signer set in the staking contract, checked against the contract
+    #[serde(default, deserialize_with = "deserialize_coinbase")]
+    pub signer: Option<H160>,
+
     #[serde(skip_deserializing)]
     pub priority: u16
 }
 
+/// 20 byte address, mixed case must match the EIP-55 checksum like full addresses
+fn deserialize_coinbase<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<H160>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|coinbase| QkcAddress::new_from_coinbase(&coinbase).map(|address| address.coinbase))
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
 impl Config {
     pub fn load() -> Result<Vec<Config>> {
-        let file = std::fs::read_to_string("config.json")?;
+        Self::parse(&std::fs::read_to_string("config.json")?)
+    }
+
+    fn parse(json: &str) -> Result<Vec<Config>> {
+        let configs: Vec<Config> = serde_json::from_str(json)?;
+        ensure!(configs.iter().all(|c| !c.rpc.urls().is_empty()), "rpc must contain at least one url");
+
+        Ok(configs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn config_test() {
+        let config = |rpc: &str| {
+            format!(r#"[{{"rpc": {rpc}, "miner_dir": ".", "miner_exe": "miner",
+                "fallback_config": {{"spawn_args": [], "path": "fallback.ini", "mine_at_free_allowances_from_max": 0}},
+                "config_files": []}}]"#)
+        };
+
+        assert_eq!(Config::parse(&config(r#""http://localhost:38391""#)).unwrap()[0].rpc.urls().len(), 1);
+        assert_eq!(Config::parse(&config(r#"["http://a:38391", "http://b:38391"]"#)).unwrap()[0].rpc.urls().len(), 2);
+        assert!(Config::parse(&config("[]")).is_err());
+
+        let root_config = |parameters: &str| {
+            serde_json::from_str::<IniParameters>(&format!(
+                r#"{{"spawn_args": [], "path": "root.ini", "root_chain": true, "mine_at_free_allowances_from_max": 0, {parameters}}}"#
+            ))
+        };
+
+        let parameters = root_config(
+            r#""stake_owner": "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000", "signer": "0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d02""#,
+        )
+        .unwrap();
+        assert_eq!(parameters.stake_owner.unwrap().to_string(), "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000");
+        assert_eq!(format!("{:?}", parameters.signer.unwrap()), "0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d02");
 
-        Ok(serde_json::from_str(&file)?)
+        assert!(root_config(r#""stake_owner": "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b""#).is_err());
+        assert!(root_config(r#""signer": "0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d0200000000""#).is_err());
+        assert!(root_config(r#""signer": "0xF0c9a075c4386ab8f08cf4529fdf77f6d2748d02""#).is_err());
     }
 }
@@ -0,0 +1,384 @@
+use anyhow::{anyhow, Result};
+use qkc_web3_rs::{
+    qkc::{Block, MinorBlockData, PoswInfo, RootBlockData},
+    types::{Branch, QkcAddress, ShardLayout, H160, H256},
+    QkcWeb3,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::Instant};
+
+/// Number of blocks PoSW allowances are counted over
+pub static POSW_WINDOW: u64 = 256;
+
+/// Windows updated more recently than this are not refetched when another address polls them
+static MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+
+pub type SharedWindow = Arc<Mutex<BlockWindow>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Chain {
+    Root,
+    Minor(Branch),
+}
+
+impl Chain {
+    /// Shard whose blocks `address` mines, addresses whose full shard keys map to the same branch share it
+    pub fn minor(address: &QkcAddress, layout: &ShardLayout) -> Result<Self> {
+        Ok(Chain::Minor(address.branch(layout)?))
+    }
+}
+
+/// One shared window per chain, handed to every address polling that chain
+#[derive(Debug, Default)]
+pub struct Windows(HashMap<Chain, SharedWindow>);
+
+impl Windows {
+    pub fn get(&mut self, chain: Chain) -> SharedWindow {
+        self.0.entry(chain.clone()).or_insert_with(|| BlockWindow::shared(chain)).clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowBlock {
+    pub height: u64,
+    pub hash: H256,
+    pub hash_prev: H256,
+    pub miner: H160,
+    pub difficulty: u128,
+    /// Node reported PoSW info of the miner, `None` if the node did not include it
+    pub posw: Option<WindowPosw>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WindowPosw {
+    pub effective_difficulty: u128,
+    pub mineable_blocks: u32,
+    pub staking_applied: bool,
+}
+
+/// Sliding window over the most recent blocks of one chain, counting the blocks mined per coinbase
+#[derive(Debug)]
+pub struct BlockWindow {
+    chain: Chain,
+    size: u64,
+    blocks: VecDeque<WindowBlock>,
+    mined: HashMap<H160, u32>,
+    mineable: HashMap<H160, u32>,
+    last_update: Option<Instant>,
+}
+
+impl BlockWindow {
+    pub fn new(chain: Chain, size: u64) -> Self {
+        Self {
+            chain,
+            size,
+            blocks: VecDeque::with_capacity(size as usize),
+            mined: HashMap::new(),
+            mineable: HashMap::new(),
+            last_update: None,
+        }
+    }
+
+    pub fn shared(chain: Chain) -> SharedWindow {
+        Arc::new(Mutex::new(Self::new(chain, POSW_WINDOW)))
+    }
+
+    pub fn latest(&self) -> Option<&WindowBlock> {
+        self.blocks.back()
+    }
+
+    /// Number of blocks in the window mined by `coinbase`
+    pub fn mined_by(&self, coinbase: &H160) -> u32 {
+        self.mined.get(coinbase).copied().unwrap_or(0)
+    }
+
+    /// Mineable blocks of `coinbase` as reported by the node on the most recent block it mined in the window
+    pub fn mineable_by(&self, coinbase: &H160) -> Option<u32> {
+        self.mineable.get(coinbase).copied()
+    }
+
+    /// Difficulty divider applied to staking miners, derived from the most recent block the node reported
+    /// with staking applied
+    pub fn posw_divider(&self) -> Option<u128> {
+        self.blocks
+            .iter()
+            .rev()
+            .filter_map(|b| b.posw.filter(|p| p.staking_applied && p.effective_difficulty > 0).map(|p| (b.difficulty, p)))
+            .map(|(difficulty, posw)| difficulty / posw.effective_difficulty)
+            .find(|divider| *divider > 1)
+    }
+
+    /// Fetches the blocks appended to the chain since the last update and evicts the ones that left the window.
+    /// The whole window is refetched if the new blocks do not build on the cached tip (e.g. after a reorg).
+    pub async fn update(&mut self, web3: &QkcWeb3) -> Result<()> {
+        if self.last_update.is_some_and(|t| t.elapsed() < MIN_UPDATE_INTERVAL) {
+            return Ok(());
+        }
+
+        let latest = self.fetch_latest(web3).await?;
+
+        if self.latest().is_some_and(|tip| tip.hash == latest.hash) {
+            self.last_update = Some(Instant::now());
+            return Ok(());
+        }
+
+        let first = latest.height.saturating_sub(self.size - 1);
+        let from = match self.latest() {
+            Some(tip) if tip.height >= first && tip.height < latest.height => tip.height + 1,
+            _ => first,
+        };
+
+        let mut blocks = self.fetch(web3, &(from..latest.height).collect::<Vec<u64>>()).await?;
+        blocks.push(latest);
+
+        if from != first && self.latest().map(|tip| &tip.hash) != Some(&blocks[0].hash_prev) {
+            debug!("Chain {:?} reorganized, refetching window", self.chain);
+            self.clear();
+            let mut window = self.fetch(web3, &(first..blocks[blocks.len() - 1].height).collect::<Vec<u64>>()).await?;
+            window.push(blocks.pop().ok_or_else(|| anyhow!("empty window"))?);
+            blocks = window;
+        } else if from == first {
+            self.clear();
+        }
+
+        for block in blocks {
+            self.push(block);
+        }
+
+        self.last_update = Some(Instant::now());
+
+        Ok(())
+    }
+
+    fn push(&mut self, block: WindowBlock) {
+        *self.mined.entry(block.miner).or_default() += 1;
+        if let Some(posw) = block.posw {
+            self.mineable.insert(block.miner, posw.mineable_blocks);
+        }
+        self.blocks.push_back(block);
+
+        while self.blocks.len() as u64 > self.size {
+            if let Some(evicted) = self.blocks.pop_front() {
+                if let Some(count) = self.mined.get_mut(&evicted.miner) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.mined.remove(&evicted.miner);
+                        self.mineable.remove(&evicted.miner);
+                    }
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.mined.clear();
+        self.mineable.clear();
+    }
+
+    async fn fetch_latest(&self, web3: &QkcWeb3) -> Result<WindowBlock> {
+        match &self.chain {
+            Chain::Root => Ok(web3.qkc().get_root_block_by_height(Block::Latest, true).await?.into()),
+            Chain::Minor(branch) => Ok(web3
+                .qkc()
+                .get_minor_block_by_height(full_shard_key(branch), Block::Latest, false, true)
+                .await?
+                .into()),
+        }
+    }
+
+    async fn fetch(&self, web3: &QkcWeb3, heights: &[u64]) -> Result<Vec<WindowBlock>> {
+        Ok(match &self.chain {
+            Chain::Root => web3
+                .qkc()
+                .get_root_blocks_by_height(heights, true)
+                .await?
+                .into_iter()
+                .map(WindowBlock::from)
+                .collect(),
+            Chain::Minor(branch) => web3
+                .qkc()
+                .get_minor_blocks_by_height(&full_shard_key(branch), heights, false, true)
+                .await?
+                .into_iter()
+                .map(WindowBlock::from)
+                .collect(),
+        })
+    }
+}
+
+fn full_shard_key(branch: &Branch) -> String {
+    format!("{:#010x}", branch.full_shard_key())
+}
+
+impl From<RootBlockData> for WindowBlock {
+    fn from(block: RootBlockData) -> Self {
+        Self {
+            height: *block.height,
+            miner: block.miner.coinbase,
+            difficulty: *block.difficulty,
+            posw: block.posw_info.as_ref().map(WindowPosw::from),
+            hash: block.hash,
+            hash_prev: block.hash_prev_block,
+        }
+    }
+}
+
+impl From<MinorBlockData> for WindowBlock {
+    fn from(block: MinorBlockData) -> Self {
+        Self {
+            height: *block.height,
+            miner: block.miner.coinbase,
+            difficulty: *block.difficulty,
+            posw: block.posw_info.as_ref().map(WindowPosw::from),
+            hash: block.hash,
+            hash_prev: block.hash_prev_minor_block,
+        }
+    }
+}
+
+impl From<&PoswInfo> for WindowPosw {
+    fn from(posw: &PoswInfo) -> Self {
+        Self {
+            effective_difficulty: *posw.effective_difficulty,
+            mineable_blocks: *posw.posw_mineable_blocks as u32,
+            staking_applied: posw.staking_applied,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use qkc_mock_node::{MockChain, MockNode, MockShard};
+
+    use super::*;
+
+    fn coinbase(address: &str) -> H160 {
+        QkcAddress::new_from_coinbase(address).unwrap().coinbase
+    }
+
+    fn block(height: u64, miner: H160) -> WindowBlock {
+        WindowBlock {
+            height,
+            hash: H256::from_low_u64_be(height),
+            hash_prev: H256::from_low_u64_be(height.saturating_sub(1)),
+            miner,
+            difficulty: 1,
+            posw: None,
+        }
+    }
+
+    #[test]
+    fn window_eviction_test() {
+        let a = coinbase("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b");
+        let b = coinbase("0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d02");
+        let mut window = BlockWindow::new(Chain::Root, 4);
+
+        for height in 0..4 {
+            window.push(block(height, if height % 2 == 0 { a } else { b }));
+        }
+
+        assert_eq!(window.mined_by(&a), 2);
+        assert_eq!(window.mined_by(&coinbase("0xF0c9A075c4386ab8F08CF4529FDF77F6D2748d02")), 2);
+        assert_eq!(window.mined_by(&coinbase("0xF0C9A075C4386AB8F08CF4529FDF77F6D2748D02")), 2);
+
+        window.push(block(4, b));
+        window.push(block(5, b));
+
+        assert_eq!(window.mined_by(&a), 1);
+        assert_eq!(window.mined_by(&b), 3);
+        assert_eq!(window.latest().map(|b| b.height), Some(5));
+    }
+
+    #[test]
+    fn shared_window_test() {
+        let layout = ShardLayout::new(&[1, 1, 1, 1]).unwrap();
+        let a = QkcAddress::new_full("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b0003c66c").unwrap();
+        let b = QkcAddress::new_full("0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d0200030000").unwrap();
+        let c = QkcAddress::new_full("0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d0200020000").unwrap();
+
+        let mut windows = Windows::default();
+        let window_a = windows.get(Chain::minor(&a, &layout).unwrap());
+        let window_b = windows.get(Chain::minor(&b, &layout).unwrap());
+        let window_c = windows.get(Chain::minor(&c, &layout).unwrap());
+
+        assert!(Arc::ptr_eq(&window_a, &window_b));
+        assert!(!Arc::ptr_eq(&window_a, &window_c));
+        assert!(Arc::ptr_eq(&windows.get(Chain::Root), &windows.get(Chain::Root)));
+        assert!(Chain::minor(&QkcAddress::new_full("0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d0200040000").unwrap(), &layout).is_err());
+    }
+
+    #[tokio::test]
+    async fn window_update_test() {
+        let a = "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b";
+        let b = "0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d02";
+
+        let mut chain = MockChain::default();
+        *chain.shard(1) = MockShard {
+            posw_mineable_blocks: Some(200),
+            ..MockShard::new(300, 1_000_000, vec![a.into(), b.into()])
+        };
+        let node = MockNode::start(chain).await.unwrap();
+        let web3 = QkcWeb3::new(node.url());
+
+        let layout = web3.qkc().shard_layout().await.unwrap();
+        let chain = Chain::minor(&QkcAddress::new(a, 1, 0).unwrap(), &layout).unwrap();
+        let mut window = BlockWindow::new(chain, POSW_WINDOW);
+        window.update(&web3).await.unwrap();
+
+        assert_eq!(window.latest().map(|b| b.height), Some(300));
+        assert_eq!(window.mined_by(&coinbase(a)), 128);
+        assert_eq!(window.mined_by(&coinbase(b)), 128);
+        assert_eq!(window.mineable_by(&coinbase(a)), Some(200));
+        assert_eq!(window.posw_divider(), Some(20));
+
+        node.chain().shard(1).advance(1);
+        window.last_update = None;
+        window.update(&web3).await.unwrap();
+        assert_eq!(window.mined_by(&coinbase(a)), 128);
+        assert_eq!(window.mined_by(&coinbase(b)), 128);
+
+        // blocks above 299 are replaced, the window is refetched
+        {
+            let mut chain = node.chain();
+            chain.shard(1).reorg(299);
+            chain.shard(1).advance(1);
+        }
+        window.last_update = None;
+        window.update(&web3).await.unwrap();
+        assert_eq!(window.latest().map(|b| b.height), Some(302));
+        assert_eq!(window.mined_by(&coinbase(a)) + window.mined_by(&coinbase(b)), POSW_WINDOW as u32);
+    }
+
+    #[tokio::test]
+    async fn single_chain_layout_test() {
+        // chain file of the README, only chain 3 is configured
+        let chain = MockChain::load(
+            r#"{
+                "root": { "height": 1000, "difficulty": 5000000, "miners": ["0x13d041434910aD2C1893c6A77537B16Cb7b8Ef5b"] },
+                "shards": { "3": { "height": 300, "miners": ["0x13d041434910aD2C1893c6A77537B16Cb7b8Ef5b"], "posw_mineable_blocks": 100 } },
+                "accounts": { "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b": { "balance": 1000000000000000000000000 } }
+            }"#,
+        )
+        .unwrap();
+        assert!(MockChain::load(r#"{"shards": {"3": {"miners": ["0x13d0"]}}}"#).is_err());
+
+        let node = MockNode::start(chain).await.unwrap();
+        let web3 = QkcWeb3::new(node.url());
+        let wallet = QkcAddress::new_full("0x13d041434910aD2C1893c6A77537B16Cb7b8Ef5b0003c66c").unwrap();
+
+        let layout = web3.qkc().shard_layout().await.unwrap();
+        let Chain::Minor(branch) = Chain::minor(&wallet, &layout).unwrap() else { panic!("expected minor chain") };
+        let account = web3.qkc().get_account_data(&wallet).await.unwrap();
+        assert_eq!(branch.full_shard_id() as u64, *account.primary.full_shard_id);
+
+        let mut window = BlockWindow::new(Chain::Minor(branch), POSW_WINDOW);
+        window.update(&web3).await.unwrap();
+        assert_eq!(window.latest().map(|b| b.height), Some(300));
+        assert_eq!(window.mineable_by(&wallet.coinbase), Some(100));
+    }
+}
@@ -0,0 +1,475 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use qkc_web3_rs::{
+    ethabi::Token,
+    qkc::{
+        AccountData, AccountShardData, Balance, Balances, Log, MinorBlockData, NetworkInfo, PoswInfo, RootBlockData,
+        BlockTransaction, Work, staking_contract
+    },
+    types::{Bytes, HexU64, QkcAddress, H160, H256, U256, QKC_TOKEN_ID}
+};
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+
+/// Number of blocks mined and mineable blocks are counted over
+pub const POSW_WINDOW: u64 = 256;
+
+/// Tag bit of work header hashes, keeps them apart from block hashes
+const WORK_TAG: u16 = 0x8000;
+
+/// In-memory QuarkChain network served by [`crate::MockNode`].
+///
+/// Blocks are generated on demand from the chain's settings, so every chain can be scripted
+/// by changing its height, difficulty and miners between requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockChain {
+    pub network_id: u64,
+    pub root: MockShard,
+    /// Shards by chain id, every chain has a single shard
+    pub shards: BTreeMap<u16, MockShard>,
+    /// Accounts by 0x prefixed coinbase
+    pub accounts: BTreeMap<String, MockAccount>
+}
+
+impl Default for MockChain {
+    fn default() -> Self {
+        Self {
+            network_id: 1,
+            root: MockShard::default(),
+            shards: (0..8).map(|chain_id| (chain_id, MockShard::default())).collect(),
+            accounts: BTreeMap::new()
+        }
+    }
+}
+
+/// Settings of the root chain or of a shard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockShard {
+    /// Height of the latest block
+    pub height: u64,
+    pub difficulty: u128,
+    /// Coinbases (0x prefixed, 20 bytes) mining the blocks in turn, the block at height `h` is mined by `miners[h % len]`
+    pub miners: Vec<String>,
+    /// PoSW extra info is reported for blocks and accounts if set
+    pub posw_mineable_blocks: Option<u64>,
+    /// Difficulty divider applied to blocks of miners with free allowances
+    pub posw_divider: u128,
+    /// Advances the height by one block every `block_time_ms` after the node was started
+    pub block_time_ms: Option<u64>,
+    /// Changes the hashes of all blocks above `fork_height`, e.g. to script a reorg
+    pub fork: u64,
+    pub fork_height: u64,
+    /// Time the blocks added every `block_time_ms` are counted from, set when the node starts
+    #[serde(skip)]
+    pub started: Option<Instant>
+}
+
+impl Default for MockShard {
+    fn default() -> Self {
+        Self {
+            height: 0,
+            difficulty: 1_000_000,
+            miners: Vec::new(),
+            posw_mineable_blocks: None,
+            posw_divider: 20,
+            block_time_ms: None,
+            fork: 0,
+            fork_height: 0,
+            started: None
+        }
+    }
+}
+
+/// State of an account, the same in every shard
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockAccount {
+    /// QKC balance in wei
+    pub balance: u128,
+    pub nonce: u64,
+    pub stakes: Option<MockStakes>
+}
+
+/// Stakes of an account in the root chain staking contract
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockStakes {
+    pub amount: u128,
+    pub unlocked: bool,
+    pub withdrawable_timestamp: u64,
+    pub signer: Option<String>
+}
+
+impl MockShard {
+    pub fn new(height: u64, difficulty: u128, miners: Vec<String>) -> Self {
+        Self {
+            height,
+            difficulty,
+            miners,
+            ..Default::default()
+        }
+    }
+
+    /// Adds `blocks` blocks on top of the current height
+    pub fn advance(&mut self, blocks: u64) {
+        self.height += blocks;
+    }
+
+    /// Replaces the blocks above `height` with blocks of a new fork
+    pub fn reorg(&mut self, height: u64) {
+        self.fork += 1;
+        self.fork_height = height;
+    }
+
+    pub(crate) fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Height of the latest block including the blocks added over time
+    pub fn latest(&self) -> u64 {
+        let advanced = match (self.block_time_ms, self.started) {
+            (Some(block_time), Some(started)) if block_time > 0 => started.elapsed().as_millis() as u64 / block_time,
+            _ => 0
+        };
+
+        self.height + advanced
+    }
+
+    fn miner(&self, height: u64) -> String {
+        match self.miners.len() {
+            0 => format!("0x{}", "00".repeat(20)),
+            len => self.miners[(height % len as u64) as usize].to_lowercase()
+        }
+    }
+
+    /// Number of blocks mined by `coinbase` in the window ending at `height`
+    pub fn mined_by(&self, coinbase: &str, height: u64) -> u64 {
+        let coinbase = coinbase.to_lowercase();
+
+        (height.saturating_sub(POSW_WINDOW - 1)..=height).filter(|h| self.miner(*h) == coinbase).count() as u64
+    }
+
+    fn hash(&self, tag: u16, height: u64) -> H256 {
+        let fork = if height > self.fork_height { self.fork } else { 0 };
+        let mut hash = H256::zero();
+        hash[..2].copy_from_slice(&tag.to_be_bytes());
+        hash[16..24].copy_from_slice(&fork.to_be_bytes());
+        hash[24..].copy_from_slice(&height.to_be_bytes());
+
+        hash
+    }
+
+    fn posw_info(&self, height: u64) -> Option<PoswInfo> {
+        let mineable = self.posw_mineable_blocks?;
+        // blocks before the current one count towards the allowances used to mine it
+        let mined = self.mined_by(&self.miner(height), height.saturating_sub(1));
+        let staking_applied = mined < mineable;
+
+        Some(PoswInfo {
+            effective_difficulty: (if staking_applied { self.difficulty / self.posw_divider.max(1) } else { self.difficulty }).into(),
+            posw_mineable_blocks: mineable.into(),
+            posw_mined_blocks: mined.into(),
+            staking_applied
+        })
+    }
+}
+
+impl MockChain {
+    /// Parses a chain file, see [`MockChain::validate`]
+    pub fn load(json: &str) -> Result<Self> {
+        let mut chain: MockChain = serde_json::from_str(json)?;
+        chain.validate()?;
+
+        Ok(chain)
+    }
+
+    /// Checks every coinbase of the chain and adds default shards for the chains missing
+    /// below the highest chain id, so that the network reports consecutive chain ids
+    pub fn validate(&mut self) -> Result<()> {
+        let shards = std::iter::once(("root".to_string(), &self.root))
+            .chain(self.shards.iter().map(|(chain_id, shard)| (format!("chain {chain_id}"), shard)));
+
+        for (chain, shard) in shards {
+            for miner in &shard.miners {
+                QkcAddress::new_from_coinbase(miner).with_context(|| format!("miner of the {chain}"))?;
+            }
+        }
+
+        for (coinbase, account) in &self.accounts {
+            QkcAddress::new_from_coinbase(coinbase).context("account")?;
+
+            if let Some(signer) = account.stakes.as_ref().and_then(|stakes| stakes.signer.as_deref()) {
+                QkcAddress::new_from_coinbase(signer).with_context(|| format!("signer of {coinbase}"))?;
+            }
+        }
+
+        self.fill_chains();
+
+        Ok(())
+    }
+
+    fn fill_chains(&mut self) {
+        let chain_size = self.chain_size();
+        (0..chain_size).for_each(|chain_id| { self.shards.entry(chain_id).or_default(); });
+    }
+
+    /// Number of chains, the highest chain id plus one
+    fn chain_size(&self) -> u16 {
+        self.shards.keys().next_back().map_or(0, |chain_id| chain_id + 1)
+    }
+
+    /// Shard of `chain_id`, created with default settings if it does not exist
+    pub fn shard(&mut self, chain_id: u16) -> &mut MockShard {
+        self.shards.entry(chain_id).or_default();
+        self.fill_chains();
+
+        self.shards.get_mut(&chain_id).expect("shard created above")
+    }
+
+    /// Account of `coinbase`, created empty if it does not exist
+    pub fn account(&mut self, coinbase: &str) -> &mut MockAccount {
+        self.accounts.entry(coinbase.to_lowercase()).or_default()
+    }
+
+    pub(crate) fn start(&mut self) {
+        self.root.start();
+        self.shards.values_mut().for_each(MockShard::start);
+    }
+
+    pub fn network_info(&self) -> NetworkInfo {
+        NetworkInfo {
+            network_id: self.network_id.into(),
+            chain_size: (self.chain_size() as u64).into(),
+            shard_sizes: (0..self.chain_size()).map(|_| 1u64.into()).collect(),
+            syncing: false,
+            mining: false,
+            shard_server_count: 1
+        }
+    }
+
+    /// Full shard ids of the chains, each with a single shard
+    pub fn full_shard_ids(&self) -> Vec<HexU64> {
+        self.shards.keys().map(|chain_id| HexU64((*chain_id as u64) << 16 | 1)).collect()
+    }
+
+    fn chain_mut(&mut self, chain_id: Option<u16>) -> Option<&mut MockShard> {
+        match chain_id {
+            None => Some(&mut self.root),
+            Some(chain_id) => self.shards.get_mut(&chain_id)
+        }
+    }
+
+    /// Work for the next block of the root chain (`None`) or of the chain `chain_id`
+    pub fn work(&self, chain_id: Option<u16>) -> Option<Work> {
+        let shard = match chain_id {
+            None => &self.root,
+            Some(chain_id) => self.shards.get(&chain_id)?
+        };
+        let height = shard.latest() + 1;
+
+        Some(Work {
+            header_hash: shard.hash(WORK_TAG | chain_id.map_or(0, |id| id + 1), height),
+            height,
+            difficulty: shard.difficulty,
+            posw_divider: shard.posw_mineable_blocks.map(|_| shard.posw_divider)
+        })
+    }
+
+    /// Adds the block of the current work if `header_hash` matches it, any nonce is accepted
+    pub fn submit_work(&mut self, chain_id: Option<u16>, header_hash: H256) -> bool {
+        let Some(work) = self.work(chain_id) else {
+            return false;
+        };
+
+        if work.header_hash != header_hash {
+            return false;
+        }
+
+        self.chain_mut(chain_id).map(|shard| shard.advance(1)).is_some()
+    }
+
+    pub fn root_block(&self, height: Option<u64>, need_extra_info: bool) -> Option<RootBlockData> {
+        let shard = &self.root;
+        let height = height.unwrap_or(shard.latest());
+
+        if height > shard.latest() {
+            return None;
+        }
+
+        let hash = shard.hash(0, height);
+
+        Some(RootBlockData {
+            id: format!("{hash:?}"),
+            hash,
+            height: height.into(),
+            id_prev_block: format!("{:?}", shard.hash(0, height.saturating_sub(1))),
+            hash_prev_block: shard.hash(0, height.saturating_sub(1)),
+            nonce: 0.into(),
+            hash_merkle_root: H256::zero(),
+            miner: address(&shard.miner(height), 0)?,
+            coinbase: vec![qkc_balance(U256::zero())],
+            difficulty: shard.difficulty.into(),
+            timestamp: height.into(),
+            size: 0.into(),
+            posw_info: shard.posw_info(height).filter(|_| need_extra_info)
+        })
+    }
+
+    pub fn minor_block(&self, chain_id: u16, height: Option<u64>, need_extra_info: bool) -> Option<MinorBlockData> {
+        let shard = self.shards.get(&chain_id)?;
+        let height = height.unwrap_or(shard.latest());
+
+        if height > shard.latest() {
+            return None;
+        }
+
+        let tag = chain_id + 1;
+        let hash = shard.hash(tag, height);
+        let full_shard_id = (chain_id as u64) << 16 | 1;
+
+        Some(MinorBlockData {
+            id: format!("{hash:?}{full_shard_id:08x}"),
+            height: height.into(),
+            hash,
+            full_shard_id: full_shard_id.into(),
+            chain_id: (chain_id as u64).into(),
+            shard_id: 0.into(),
+            hash_prev_minor_block: shard.hash(tag, height.saturating_sub(1)),
+            id_prev_minor_block: format!("{:?}{full_shard_id:08x}", shard.hash(tag, height.saturating_sub(1))),
+            hash_prev_root_block: self.root.hash(0, self.root.latest()),
+            nonce: 0.into(),
+            hash_merkle_root: H256::zero(),
+            hash_evm_state_root: H256::zero(),
+            miner: address(&shard.miner(height), chain_id)?,
+            coinbase: vec![qkc_balance(U256::zero())],
+            difficulty: shard.difficulty.into(),
+            extra_data: Bytes::default(),
+            gas_limit: 12_000_000.into(),
+            gas_used: 0.into(),
+            timestamp: height.into(),
+            size: 0.into(),
+            transactions: Vec::<BlockTransaction>::new(),
+            posw_info: shard.posw_info(height).filter(|_| need_extra_info)
+        })
+    }
+
+    /// Logs of the blocks from `from` to `to` of the chain `chain_id`, every block contains a single
+    /// log emitted by the zero address with the block hash as its topic
+    pub fn logs(&self, chain_id: u16, from: u64, to: u64) -> Vec<Log> {
+        let Some(shard) = self.shards.get(&chain_id) else {
+            return Vec::new();
+        };
+
+        (from..=to.min(shard.latest()))
+            .map(|height| {
+                let hash = shard.hash(chain_id + 1, height);
+
+                Log {
+                    log_index: 0.into(),
+                    transaction_index: 0.into(),
+                    transaction_hash: hash,
+                    block_hash: hash,
+                    block_height: height.into(),
+                    address: H160::zero(),
+                    data: Bytes::default(),
+                    topics: vec![hash],
+                    removed: false
+                }
+            })
+            .collect()
+    }
+
+    pub fn balances(&self, address: &QkcAddress) -> Balances {
+        let shard = self.account_shard(address);
+
+        Balances {
+            branch: shard.full_shard_id,
+            full_shard_id: shard.full_shard_id,
+            shard_id: shard.shard_id,
+            chain_id: shard.chain_id,
+            balances: shard.balances
+        }
+    }
+
+    pub fn transaction_count(&self, address: &QkcAddress) -> u64 {
+        self.accounts.get(&address.coinbase()).map(|account| account.nonce).unwrap_or_default()
+    }
+
+    pub fn account_data(&self, address: &QkcAddress) -> AccountData {
+        AccountData {
+            primary: self.account_shard(address),
+            shards: None
+        }
+    }
+
+    fn account_shard(&self, address: &QkcAddress) -> AccountShardData {
+        let chain_id = address.chain_id();
+        let account = self.accounts.get(&address.coinbase()).cloned().unwrap_or_default();
+        let shard = self.shards.get(&chain_id);
+        let posw = shard.and_then(|shard| shard.posw_mineable_blocks.map(|mineable| (shard.mined_by(&address.coinbase(), shard.latest()), mineable)));
+
+        AccountShardData {
+            full_shard_id: ((chain_id as u64) << 16 | 1).into(),
+            shard_id: 0.into(),
+            chain_id: (chain_id as u64).into(),
+            balances: vec![qkc_balance(account.balance.into())],
+            transaction_count: account.nonce.into(),
+            is_contract: false,
+            mined_blocks: posw.map(|(mined, _)| mined.into()),
+            posw_mineable_blocks: posw.map(|(_, mineable)| mineable.into())
+        }
+    }
+
+    /// Output of a call to the root chain staking contract, empty for any other contract
+    pub fn call(&self, to: &QkcAddress, data: &[u8]) -> Vec<u8> {
+        let contract = staking_contract().expect("valid staking contract abi");
+
+        if to.to_string() != qkc_web3_rs::qkc::ROOT_STAKING_CONTRACT || data.len() < 4 {
+            return Vec::new();
+        }
+
+        let Some(function) = contract.functions().find(|f| f.short_signature() == data[..4]) else {
+            return Vec::new();
+        };
+
+        let staker = match function.decode_input(&data[4..]).ok().as_deref() {
+            Some([Token::Address(staker)]) => *staker,
+            _ => return Vec::new()
+        };
+
+        let stakes = self.accounts.get(&format!("{staker:?}")).and_then(|account| account.stakes.clone()).unwrap_or_default();
+        let signer = stakes.signer.as_deref().and_then(|signer| signer.parse::<H160>().ok()).unwrap_or_default();
+
+        let tokens = match function.name.as_str() {
+            "stakes" => vec![
+                Token::Bool(stakes.unlocked),
+                Token::Uint(stakes.withdrawable_timestamp.into()),
+                Token::Uint(stakes.amount.into()),
+                Token::Address(signer)
+            ],
+            "getLockedStakes" if !stakes.unlocked => vec![
+                Token::Uint(stakes.amount.into()),
+                Token::Address(if signer.is_zero() { staker } else { signer })
+            ],
+            "getLockedStakes" => vec![Token::Uint(U256::zero()), Token::Address(H160::zero())],
+            _ => return Vec::new()
+        };
+
+        qkc_web3_rs::ethabi::encode(&tokens)
+    }
+}
+
+/// Address of a miner, `None` if the coinbase was changed to an invalid one after [`MockChain::validate`]
+fn address(coinbase: &str, chain_id: u16) -> Option<QkcAddress> {
+    QkcAddress::new(coinbase, chain_id, 0).ok()
+}
+
+fn qkc_balance(balance: U256) -> Balance {
+    Balance {
+        token_id: QKC_TOKEN_ID.into(),
+        token_str: "QKC".to_string(),
+        balance
+    }
+}
@@ -0,0 +1,8 @@
+//! Offline QuarkChain JSON-RPC node serving a scriptable in-memory chain, for tests and local runs
+//! of the mining assistant without network access.
+
+mod chain;
+mod server;
+
+pub use chain::{MockChain, MockShard, MockAccount, MockStakes, POSW_WINDOW};
+pub use server::MockNode;
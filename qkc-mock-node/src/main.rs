@@ -0,0 +1,23 @@
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use qkc_mock_node::{MockChain, MockNode};
+
+/// Usage: `qkc-mock-node [address] [chain.json]`, e.g. `qkc-mock-node 127.0.0.1:38391 chain.json`.
+/// The chain file is a serialized `MockChain`, missing fields use their defaults and invalid coinbases are rejected.
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let addr: SocketAddr = args.next().as_deref().unwrap_or("127.0.0.1:38391").parse()?;
+
+    let chain: MockChain = match args.next() {
+        Some(path) => MockChain::load(&std::fs::read_to_string(path)?)?,
+        None => MockChain::default()
+    };
+
+    let node = MockNode::bind(chain, addr).await?;
+    println!("Serving mock QuarkChain node on {}", node.url());
+    node.wait().await;
+
+    Ok(())
+}
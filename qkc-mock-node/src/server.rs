@@ -0,0 +1,162 @@
+use std::{net::SocketAddr, sync::{Arc, Mutex, MutexGuard}};
+
+use axum::{Json, Router, extract::State, routing::post};
+use qkc_web3_rs::types::{Bytes, HexU64, QkcAddress};
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::chain::{MockChain, MockShard};
+
+type SharedChain = Arc<Mutex<MockChain>>;
+
+/// JSON-RPC server answering requests from a [`MockChain`]
+#[derive(Debug)]
+pub struct MockNode {
+    chain: SharedChain,
+    addr: SocketAddr,
+    handle: JoinHandle<()>
+}
+
+impl MockNode {
+    /// Serves `chain` on a free local port
+    pub async fn start(chain: MockChain) -> std::io::Result<Self> {
+        Self::bind(chain, SocketAddr::from(([127, 0, 0, 1], 0))).await
+    }
+
+    /// Serves `chain` on `addr`, fails if the chain is invalid (see [`MockChain::validate`])
+    pub async fn bind(mut chain: MockChain, addr: SocketAddr) -> std::io::Result<Self> {
+        chain.validate().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{e:#}")))?;
+        chain.start();
+
+        let chain = Arc::new(Mutex::new(chain));
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let router = Router::new().route("/", post(handle)).with_state(chain.clone());
+
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, router).await.ok();
+        });
+
+        Ok(Self {
+            chain,
+            addr,
+            handle
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Chain served by the node, changes apply to the following requests
+    pub fn chain(&self) -> MutexGuard<'_, MockChain> {
+        self.chain.lock().unwrap()
+    }
+
+    /// Runs until the server fails
+    pub async fn wait(mut self) {
+        (&mut self.handle).await.ok();
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle(State(chain): State<SharedChain>, Json(req): Json<Value>) -> Json<Value> {
+    let mut chain = chain.lock().unwrap();
+
+    Json(match req {
+        Value::Array(reqs) => reqs.iter().map(|req| respond(&mut chain, req)).collect(),
+        req => respond(&mut chain, &req)
+    })
+}
+
+fn respond(chain: &mut MockChain, req: &Value) -> Value {
+    let params = req["params"].as_array().cloned().unwrap_or_default();
+
+    match answer(chain, req["method"].as_str().unwrap_or_default(), &params) {
+        Ok(result) => json!({"jsonrpc": "2.0", "result": result, "id": req["id"]}),
+        Err((code, message)) => json!({"jsonrpc": "2.0", "error": {"code": code, "message": message}, "id": req["id"]})
+    }
+}
+
+type Answer = Result<Value, (i64, String)>;
+
+fn answer(chain: &mut MockChain, method: &str, params: &[Value]) -> Answer {
+    let result = match method {
+        "networkInfo" => serde_json::to_value(chain.network_info()),
+        "getFullShardIds" => serde_json::to_value(chain.full_shard_ids()),
+        "getTransactionCount" => serde_json::to_value(HexU64(chain.transaction_count(&address(params.first())?))),
+        "getBalances" => serde_json::to_value(chain.balances(&address(params.first())?)),
+        "getAccountData" => serde_json::to_value(chain.account_data(&address(params.first())?)),
+        "getRootBlockByHeight" => serde_json::to_value(chain.root_block(height(params.first())?, flag(params.get(1)))),
+        "getMinorBlockByHeight" => {
+            let full_shard_key = full_shard_key(params.first())?.ok_or_else(|| invalid("full shard key"))?;
+
+            serde_json::to_value(chain.minor_block((full_shard_key >> 16) as u16, height(params.get(1))?, flag(params.get(3))))
+        },
+        "getLogs" => {
+            let filter = params.first().ok_or_else(|| invalid("filter"))?;
+            let full_shard_key = full_shard_key(params.get(1))?.ok_or_else(|| invalid("full shard key"))?;
+            let chain_id = (full_shard_key >> 16) as u16;
+            let latest = chain.shards.get(&chain_id).map(MockShard::latest).unwrap_or_default();
+            let from = height(filter.get("fromBlock"))?.unwrap_or(latest);
+            let to = height(filter.get("toBlock"))?.unwrap_or(latest);
+
+            serde_json::to_value(chain.logs(chain_id, from, to))
+        },
+        "getWork" => serde_json::to_value(chain.work(full_shard_key(params.first())?.map(|key| (key >> 16) as u16))),
+        "submitWork" => {
+            let header_hash = params.get(1).and_then(|hash| serde_json::from_value(hash.clone()).ok()).ok_or_else(|| invalid("header hash"))?;
+
+            serde_json::to_value(chain.submit_work(full_shard_key(params.first())?.map(|key| (key >> 16) as u16), header_hash))
+        },
+        "call" => {
+            let call = params.first().ok_or_else(|| invalid("call"))?;
+            let data: Bytes = serde_json::from_value(call["data"].clone()).map_err(|_| invalid("data"))?;
+
+            serde_json::to_value(Bytes(chain.call(&address(Some(&call["to"]))?, &data)))
+        },
+        _ => return Err((-32601, "Method not found".to_string()))
+    };
+
+    result.map_err(|e| (-32603, e.to_string()))
+}
+
+fn invalid(param: &str) -> (i64, String) {
+    (-32602, format!("Invalid params: {param}"))
+}
+
+fn address(param: Option<&Value>) -> Result<QkcAddress, (i64, String)> {
+    param.and_then(Value::as_str)
+        .and_then(|address| address.parse().ok())
+        .ok_or_else(|| invalid("address"))
+}
+
+/// Full shard key parameter, `None` for the root chain
+fn full_shard_key(param: Option<&Value>) -> Result<Option<u32>, (i64, String)> {
+    match param {
+        None | Some(Value::Null) => Ok(None),
+        Some(key) => key.as_str().and_then(|key| u32::from_str_radix(key.strip_prefix("0x")?, 16).ok()).map(Some).ok_or_else(|| invalid("full shard key"))
+    }
+}
+
+/// Block height parameter, `None` for the latest block
+fn height(param: Option<&Value>) -> Result<Option<u64>, (i64, String)> {
+    match param {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(latest)) if latest == "latest" => Ok(None),
+        Some(height) => serde_json::from_value::<HexU64>(height.clone()).map(|height| Some(height.0)).map_err(|_| invalid("height"))
+    }
+}
+
+fn flag(param: Option<&Value>) -> bool {
+    param.and_then(Value::as_bool).unwrap_or(false)
+}
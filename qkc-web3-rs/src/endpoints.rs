@@ -0,0 +1,150 @@
+use std::{sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+/// Endpoints whose health was checked more recently than this are not checked again
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest time a failing endpoint is skipped before it is tried again
+pub const MAX_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Health of a single RPC endpoint
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealth {
+    /// Consecutive failed requests
+    pub failures: u32,
+    /// Root block height reported by the last health check
+    pub root_height: Option<u64>,
+    /// Minor block height of the checked shard reported by the last health check, if a shard is checked
+    pub minor_height: Option<u64>,
+    unhealthy_until: Option<Instant>
+}
+
+impl EndpointHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.unhealthy_until.is_none_or(|until| until <= Instant::now())
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    health: Mutex<EndpointHealth>
+}
+
+/// List of RPC endpoints of which requests use the healthy one with the highest chain first
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    endpoints: Arc<Vec<Endpoint>>,
+    last_check: Arc<Mutex<Option<Instant>>>
+}
+
+impl Endpoints {
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            endpoints: Arc::new(urls.into_iter().map(|url| Endpoint { url, health: Mutex::default() }).collect()),
+            last_check: Arc::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.endpoints.iter().map(|e| e.url.as_str())
+    }
+
+    pub fn health(&self, url: &str) -> Option<EndpointHealth> {
+        self.endpoints.iter().find(|e| e.url == url).map(|e| e.health.lock().unwrap().clone())
+    }
+
+    /// Urls in the order they should be tried: healthy endpoints first, ordered by the
+    /// reported root height, then the minor height and then by their position in the list
+    pub(crate) fn ordered(&self) -> Vec<&str> {
+        let mut endpoints: Vec<_> = self.endpoints.iter().enumerate().map(|(i, e)| {
+            let health = e.health.lock().unwrap();
+            (i, health.is_healthy(), (health.root_height, health.minor_height), e.url.as_str())
+        }).collect();
+
+        endpoints.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+
+        endpoints.into_iter().map(|e| e.3).collect()
+    }
+
+    /// Returns `true` once per [`HEALTH_CHECK_INTERVAL`] if more than one endpoint is configured
+    pub(crate) fn check_due(&self) -> bool {
+        if self.endpoints.len() < 2 {
+            return false;
+        }
+
+        let mut last_check = self.last_check.lock().unwrap();
+
+        if last_check.is_some_and(|t| t.elapsed() < HEALTH_CHECK_INTERVAL) {
+            false
+        } else {
+            *last_check = Some(Instant::now());
+            true
+        }
+    }
+
+    pub(crate) fn report_height(&self, url: &str, root_height: u64, minor_height: Option<u64>) {
+        self.update(url, |health| {
+            health.root_height = Some(root_height);
+            health.minor_height = minor_height;
+        });
+    }
+
+    pub(crate) fn report_success(&self, url: &str) {
+        self.update(url, |health| {
+            health.failures = 0;
+            health.unhealthy_until = None;
+        });
+    }
+
+    pub(crate) fn report_failure(&self, url: &str) {
+        self.update(url, |health| {
+            health.failures += 1;
+            let cooldown = Duration::from_secs(1 << health.failures.min(6)).min(MAX_COOLDOWN);
+            health.unhealthy_until = Some(Instant::now() + cooldown);
+        });
+    }
+
+    fn update(&self, url: &str, f: impl FnOnce(&mut EndpointHealth)) {
+        if let Some(endpoint) = self.endpoints.iter().find(|e| e.url == url) {
+            f(&mut endpoint.health.lock().unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Endpoints;
+
+    #[test]
+    fn ordering_test() {
+        let endpoints = Endpoints::new(vec!["a".into(), "b".into(), "c".into()]);
+
+        assert_eq!(endpoints.ordered(), vec!["a", "b", "c"]);
+
+        endpoints.report_height("a", 10, None);
+        endpoints.report_height("b", 12, None);
+        endpoints.report_height("c", 12, None);
+        assert_eq!(endpoints.ordered(), vec!["b", "c", "a"]);
+
+        // same root chain, c is ahead on the checked shard
+        endpoints.report_height("b", 12, Some(100));
+        endpoints.report_height("c", 12, Some(101));
+        assert_eq!(endpoints.ordered(), vec!["c", "b", "a"]);
+        endpoints.report_height("c", 12, Some(100));
+
+        endpoints.report_failure("b");
+        assert_eq!(endpoints.ordered(), vec!["c", "a", "b"]);
+        assert_eq!(endpoints.health("b").unwrap().failures, 1);
+
+        endpoints.report_success("b");
+        assert_eq!(endpoints.ordered(), vec!["b", "c", "a"]);
+    }
+}
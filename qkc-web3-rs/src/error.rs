@@ -0,0 +1,127 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by every `Qkc` method.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request never produced an HTTP response (connection refused, DNS, timeout, ...)
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    /// The node answered with a non-success HTTP status and no JSON-RPC error object
+    #[error("http status {status}: {body}")]
+    HttpStatus {
+        status: u16,
+        body: String
+    },
+
+    /// The node answered with a JSON-RPC `error` object
+    #[error("rpc error: {0}")]
+    Rpc(#[from] RpcError),
+
+    /// The response could not be deserialized into the expected type
+    #[error("failed to decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    /// The response was well formed but neither contained a `result` nor an `error`
+    #[error("node returned an empty result")]
+    EmptyResult,
+
+    /// A value inside the response was not in the expected format
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+
+    #[error("abi error: {0}")]
+    Abi(#[from] ethabi::Error),
+
+    #[error("no rpc endpoint configured")]
+    NoEndpoint,
+
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+
+    /// The websocket connection was closed before the response arrived
+    #[error("websocket connection closed")]
+    ConnectionClosed,
+
+    /// No response arrived within the policy's timeout
+    #[error("request timed out")]
+    Timeout,
+
+    /// Subscriptions are only available over a websocket connection
+    #[error("subscriptions require a websocket connection")]
+    SubscriptionsUnsupported,
+}
+
+/// Coarse classification of [`Error`], e.g. to decide which errors are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Transport,
+    Timeout,
+    HttpStatus,
+    Rpc,
+    Decode,
+    EmptyResult,
+    InvalidResponse,
+    Abi,
+    NoEndpoint,
+    WebSocket,
+    ConnectionClosed,
+    SubscriptionsUnsupported
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Transport(e) if e.is_timeout() => ErrorKind::Timeout,
+            Error::Transport(_) => ErrorKind::Transport,
+            Error::HttpStatus { .. } => ErrorKind::HttpStatus,
+            Error::Rpc(_) => ErrorKind::Rpc,
+            Error::Decode(_) => ErrorKind::Decode,
+            Error::EmptyResult => ErrorKind::EmptyResult,
+            Error::InvalidResponse(_) => ErrorKind::InvalidResponse,
+            Error::Abi(_) => ErrorKind::Abi,
+            Error::NoEndpoint => ErrorKind::NoEndpoint,
+            Error::WebSocket(_) => ErrorKind::WebSocket,
+            Error::ConnectionClosed => ErrorKind::ConnectionClosed,
+            Error::Timeout => ErrorKind::Timeout,
+            Error::SubscriptionsUnsupported => ErrorKind::SubscriptionsUnsupported
+        }
+    }
+
+    /// Whether the endpoint rather than the request failed: it could not be reached, timed out, was rate
+    /// limited (429) or failed internally (5xx). Other 4xx statuses (bad request, unauthorized, not found)
+    /// would fail the same way again on every endpoint
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::HttpStatus { status, .. } => *status == 429 || *status >= 500,
+            e => matches!(e.kind(), ErrorKind::Transport | ErrorKind::Timeout)
+        }
+    }
+}
+
+/// JSON-RPC error object as returned by the node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)?;
+
+        if let Some(data) = &self.data {
+            write!(f, ": {data}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for RpcError {}
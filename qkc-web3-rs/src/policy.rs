@@ -0,0 +1,142 @@
+use std::{future::Future, time::Duration};
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// Timeout and retry behaviour applied to every request sent by [`crate::qkc::Qkc`]
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Timeout of a single attempt, including reading the response body
+    pub timeout: Duration,
+    /// Attempts made after the first one failed, 0 disables retries
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_backoff: Duration,
+    /// Fraction of the delay that is randomized, e.g. 0.5 waits between 50% and 100% of the backoff
+    pub jitter: f64,
+    /// Error kinds an attempt is retried on, [`ErrorKind::HttpStatus`] only covers 429 and 5xx statuses
+    pub retry_on: Vec<ErrorKind>
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            jitter: 0.5,
+            retry_on: vec![ErrorKind::Transport, ErrorKind::Timeout, ErrorKind::HttpStatus, ErrorKind::WebSocket, ErrorKind::ConnectionClosed]
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that sends every request exactly once
+    pub fn no_retries() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before retry number `attempt` (starting at 0)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+
+        backoff.mul_f64(1.0 - jitter * rand::random::<f64>())
+    }
+
+    /// Whether an attempt that failed with `e` is retried, http statuses only if [`Error::is_transient`]
+    pub fn retries(&self, e: &Error) -> bool {
+        self.retry_on.contains(&e.kind()) && match e {
+            Error::HttpStatus { .. } => e.is_transient(),
+            _ => true
+        }
+    }
+
+    pub(crate) async fn run<T, F, Fut>(&self, f: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>
+    {
+        let mut attempt = 0;
+
+        loop {
+            match f().await {
+                Err(e) if attempt < self.max_retries && self.retries(&e) => {
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                },
+                res => return res
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::atomic::{AtomicU32, Ordering}, time::Duration};
+
+    use super::RetryPolicy;
+    use crate::{Error, RpcError};
+
+    #[test]
+    fn backoff_test() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), Duration::from_secs(10));
+
+        let policy = RetryPolicy::default();
+        for attempt in 0..5 {
+            let backoff = policy.backoff(attempt);
+            assert!(backoff <= policy.max_backoff && backoff >= policy.initial_backoff / 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_test() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let attempts = AtomicU32::new(0);
+
+        let res = policy.run(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Error::HttpStatus { status: 503, body: String::new() })
+        }).await;
+
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+
+        attempts.store(0, Ordering::SeqCst);
+        let res = policy.run(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Error::Rpc(RpcError { code: -32602, message: "Invalid params".into(), data: None }))
+        }).await;
+
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        attempts.store(0, Ordering::SeqCst);
+        let res = policy.run(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Error::HttpStatus { status: 404, body: String::new() })
+        }).await;
+
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert!(policy.retries(&Error::HttpStatus { status: 429, body: String::new() }));
+        assert!(!policy.retries(&Error::HttpStatus { status: 401, body: String::new() }));
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+
+use ethabi::{Event, RawLog};
+use serde::{Serialize, Deserialize};
+
+use crate::{error::Result, types::{QkcAddress, HexU64, Bytes, H160, H256}};
+
+use super::{
+    Method, MinorBlockData, Qkc, Request,
+    stream::{BlockEvent, BlockFollower, MinorSource, MAX_REORG_DEPTH}
+};
+
+/// Parameters of the `getLogs` RPC
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    /// First block height to include, the latest block if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_block: Option<HexU64>,
+    /// Last block height to include, the latest block if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_block: Option<HexU64>,
+    /// Contracts whose logs are returned, all contracts if empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<QkcAddress>,
+    /// Topics by position, `None` matches any topic and multiple topics match either of them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<Option<Vec<H256>>>
+}
+
+impl LogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_block(mut self, height: u64) -> Self {
+        self.from_block = Some(height.into());
+        self
+    }
+
+    pub fn to_block(mut self, height: u64) -> Self {
+        self.to_block = Some(height.into());
+        self
+    }
+
+    pub fn address(mut self, address: QkcAddress) -> Self {
+        self.address.push(address);
+        self
+    }
+
+    /// Matches `topics` at position `index`, e.g. the event signature at 0
+    pub fn topic(mut self, index: usize, topics: Vec<H256>) -> Self {
+        if self.topics.len() <= index {
+            self.topics.resize(index + 1, None);
+        }
+
+        self.topics[index] = Some(topics);
+        self
+    }
+
+    /// Matches logs of `event`
+    pub fn event(self, event: &Event) -> Self {
+        self.topic(0, vec![event.signature()])
+    }
+}
+
+/// Log emitted by a contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub log_index: HexU64,
+    pub transaction_index: HexU64,
+    pub transaction_hash: H256,
+    pub block_hash: H256,
+    pub block_height: HexU64,
+    /// Contract that emitted the log
+    pub address: H160,
+    pub data: Bytes,
+    pub topics: Vec<H256>,
+    #[serde(default)]
+    pub removed: bool
+}
+
+impl Log {
+    /// Decodes the log's parameters as `event`
+    pub fn decode(&self, event: &Event) -> Result<ethabi::Log> {
+        let raw = RawLog {
+            topics: self.topics.clone(),
+            data: self.data.0.clone()
+        };
+
+        Ok(event.parse_log(raw)?)
+    }
+}
+
+/// Polls the logs matching a filter in new blocks, every log is returned once.
+///
+/// Blocks are followed like [`Qkc::minor_block_stream`]: when blocks are replaced by a reorg,
+/// the logs already returned from them are returned again with `removed` set, followed by
+/// the logs of the new blocks. Logs more than [`MAX_REORG_DEPTH`] blocks below the latest
+/// block are never retracted.
+#[derive(Debug, Clone)]
+pub struct LogPoller {
+    filter: LogFilter,
+    full_shard_key: String,
+    next_height: Option<u64>,
+    blocks: BlockFollower<MinorBlockData>,
+    /// Height of the latest block that logs are searched up to
+    latest: Option<u64>,
+    /// Logs returned from the recent blocks that a reorg could replace
+    recent: VecDeque<Log>,
+    /// Logs of replaced blocks that are returned with the next logs
+    removed: Vec<Log>
+}
+
+impl LogPoller {
+    /// Starts at the filter's `from_block`, or at the latest block if it is not set.
+    /// The filter's `to_block` is ignored.
+    pub fn new(filter: LogFilter, full_shard_key: String) -> Self {
+        Self {
+            next_height: filter.from_block.map(|height| height.0),
+            filter,
+            full_shard_key,
+            blocks: BlockFollower::new(),
+            latest: None,
+            recent: VecDeque::new(),
+            removed: Vec::new()
+        }
+    }
+
+    /// Height of the next block that will be searched for logs
+    pub fn next_height(&self) -> Option<u64> {
+        self.next_height
+    }
+
+    /// Logs in the blocks added since the last poll, preceded by the logs of replaced blocks
+    pub async fn poll(&mut self, qkc: &Qkc) -> Result<Vec<Log>> {
+        let source = MinorSource {
+            qkc: qkc.clone(),
+            full_shard_key: self.full_shard_key.clone(),
+            include_transactions: false
+        };
+        self.blocks.poll(&source).await?;
+
+        while let Some(event) = self.blocks.pop() {
+            match event {
+                BlockEvent::Block(block) => self.latest = Some(*block.height),
+                BlockEvent::Reorg { height, .. } => {
+                    let (removed, kept) = self.recent.drain(..).partition(|log| *log.block_height > height);
+                    self.recent = kept;
+                    self.removed.extend(removed.into_iter().map(|log| Log { removed: true, ..log }));
+                    self.next_height = self.next_height.map(|next| next.min(height + 1));
+                }
+            }
+        }
+
+        let Some(latest) = self.latest else {
+            return Ok(Vec::new());
+        };
+        let from = self.next_height.unwrap_or(latest);
+
+        let mut logs = Vec::new();
+        if from <= latest {
+            let filter = LogFilter {
+                from_block: Some(from.into()),
+                to_block: Some(latest.into()),
+                ..self.filter.clone()
+            };
+
+            logs = qkc.get_logs(&filter, &self.full_shard_key).await?;
+            self.next_height = Some(latest + 1);
+        }
+
+        self.recent.extend(logs.iter().cloned());
+        self.recent.retain(|log| *log.block_height + MAX_REORG_DEPTH as u64 > latest);
+
+        Ok(self.removed.drain(..).chain(logs).collect())
+    }
+}
+
+impl Qkc {
+    /// Logs matching `filter` in the shard of `full_shard_key`
+    pub async fn get_logs(&self, filter: &LogFilter, full_shard_key: &str) -> Result<Vec<Log>> {
+        let req = Request::<serde_json::Value>::new(Method::GetLogs, Some(vec![serde_json::to_value(filter)?, full_shard_key.into()]));
+
+        self.send(&req).await
+    }
+
+    /// Poller of the logs matching `filter` in the shard of `full_shard_key`, see [`LogPoller`]
+    pub fn log_poller(&self, filter: LogFilter, full_shard_key: &str) -> LogPoller {
+        LogPoller::new(filter, full_shard_key.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethabi::{EventParam, ParamType, Token};
+
+    use super::*;
+
+    fn transfer_event() -> Event {
+        let param = |name: &str, kind, indexed| EventParam { name: name.into(), kind, indexed };
+
+        Event {
+            name: "Transfer".into(),
+            inputs: vec![
+                param("from", ParamType::Address, true),
+                param("to", ParamType::Address, true),
+                param("value", ParamType::Uint(256), false)
+            ],
+            anonymous: false
+        }
+    }
+
+    #[test]
+    fn log_decode_test() {
+        let event = transfer_event();
+        let hash = format!("0x{}", "11".repeat(32));
+        let from = H160::repeat_byte(0x22);
+        let to = H160::repeat_byte(0x33);
+
+        let log: Log = serde_json::from_value(serde_json::json!({
+            "logIndex": "0x0", "transactionIndex": "0x1", "transactionHash": hash, "blockHash": hash,
+            "blockNumber": "0x10", "blockHeight": "0x10", "address": format!("{:?}", H160::repeat_byte(0x44)),
+            "recipient": format!("{:?}", H160::repeat_byte(0x44)),
+            "data": format!("0x{:064x}", 1000),
+            "topics": [format!("{:?}", event.signature()), format!("{:?}", H256::from(from)), format!("{:?}", H256::from(to))],
+            "removed": false
+        })).unwrap();
+
+        assert_eq!(*log.block_height, 16);
+        let decoded = log.decode(&event).unwrap();
+        assert_eq!(decoded.params[0].value, Token::Address(from));
+        assert_eq!(decoded.params[1].value, Token::Address(to));
+        assert_eq!(decoded.params[2].value, Token::Uint(1000.into()));
+
+        let filter = LogFilter::new()
+            .from_block(16)
+            .address(QkcAddress::new_full("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000").unwrap())
+            .event(&event)
+            .topic(2, vec![H256::from(to)]);
+
+        assert_eq!(serde_json::to_value(&filter).unwrap(), serde_json::json!({
+            "fromBlock": "0x10",
+            "address": ["0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000"],
+            "topics": [[format!("{:?}", event.signature())], null, [format!("{:?}", H256::from(to))]]
+        }));
+    }
+}
@@ -1,140 +1,424 @@
-use std::{vec, io::BufReader};
+use std::{sync::Arc, vec};
 
-use serde::{Serialize, Deserialize};
-use anyhow::Result;
+use ethabi::{Contract, Token};
 
-use crate::types::QkcAddress;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::{types::{QkcAddress, Branch, ShardLayout, HexU64, HexU128, Bytes, SignedTransaction, H160, H256, U256, QKC_TOKEN_ID}, error::{Error, Result, RpcError}, policy::RetryPolicy, transport::{Transport, WsClient, Subscription}};
+
+mod logs;
+mod staking;
+mod stream;
+
+pub use logs::{LogFilter, Log, LogPoller};
+pub use stream::{BlockEvent, ChainBlock, MAX_REORG_DEPTH};
+pub use staking::{StakingCall, LockedStakes, staking_contract, ROOT_STAKING_CONTRACT, STAKING_GAS};
 
 #[derive(Debug, Clone)]
 pub struct Qkc {
-    pub(crate) client: reqwest::Client,
-    pub(crate) url: String,
+    pub(crate) transport: Arc<dyn Transport>,
+    pub(crate) policy: RetryPolicy,
 }
 
+/// Max number of calls sent in a single batch request, larger batches are split
+pub const MAX_BATCH_SIZE: usize = 128;
+
+/// Gas limit of the calls made by [`Qkc::call_function`]
+pub const CALL_GAS: u64 = 1_000_000;
+
 impl Qkc {
+    /// Sends `req` over the transport, arrays are sent as batch
+    async fn post<R: Serialize + ?Sized, T: DeserializeOwned>(&self, req: &R) -> Result<T> {
+        let res = match serde_json::to_value(req)? {
+            serde_json::Value::Array(reqs) => self.transport.send_batch(reqs).await?,
+            req => self.transport.send(req).await?
+        };
+
+        Ok(serde_json::from_value(res)?)
+    }
+
+    async fn send<R: Serialize, T: DeserializeOwned>(&self, req: &R) -> Result<T> {
+        self.policy.run(|| async { self.post::<_, RpcResponse<T>>(req).await?.into_result() }).await
+    }
+
+    /// Sends all `calls` in one JSON-RPC array request per [`MAX_BATCH_SIZE`] calls.
+    ///
+    /// The outer error is returned if a request as a whole failed, the inner results are
+    /// correlated by id and returned in the same order as `calls`.
+    pub async fn batch<T: DeserializeOwned>(&self, calls: &[BatchCall]) -> Result<Vec<Result<T>>> {
+        let mut results = Vec::with_capacity(calls.len());
+
+        for chunk in calls.chunks(MAX_BATCH_SIZE) {
+            let req: Vec<Request<serde_json::Value>> = chunk.iter().enumerate().map(|(id, call)| Request {
+                jsonrpc: "2.0".into(),
+                method: call.method,
+                params: call.params.clone(),
+                id
+            }).collect();
+
+            let res = self.policy.run(|| async {
+                match self.post::<_, BatchResponse>(&req).await? {
+                    BatchResponse::Batch(res) => Ok(res),
+                    // nodes reply with a single error object if the batch itself was rejected
+                    BatchResponse::Single(res) => Err(res.into_result().err().unwrap_or(Error::EmptyResult))
+                }
+            }).await?;
+
+            let mut chunk_results: Vec<Option<Result<T>>> = (0..chunk.len()).map(|_| None).collect();
+
+            for res in res {
+                match res.id {
+                    Some(id) if id < chunk_results.len() => {
+                        chunk_results[id] = Some(res.into_result().and_then(|res| Ok(serde_json::from_value(res)?)));
+                    },
+                    _ => return Err(Error::InvalidResponse(format!("unexpected batch response id {:?}", res.id)))
+                }
+            }
+
+            results.extend(chunk_results.into_iter().map(|res| res.unwrap_or(Err(Error::EmptyResult))));
+        }
+
+        Ok(results)
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
     pub async fn network_info(&self) -> Result<NetworkInfo> {
         let req = Request::<String>::new(Method::NetworkInfo, None);
 
-        let res: RpcResponse<NetworkInfo> = self.client.post(&self.url).json(&req).send().await?.json().await?;
-        
-        Ok(res.result)
+        self.send(&req).await
+    }
+
+    pub async fn shard_layout(&self) -> Result<ShardLayout> {
+        self.network_info().await?.shard_layout()
     }
 
     pub async fn get_transaction_count(&self, address: &QkcAddress) -> Result<u128> {
         let req = Request::new(Method::GetTransactionCount, Some(vec![address.to_string()]));
 
-        let res: RpcResponse<String> = self.client.post(&self.url).json(&req).send().await?.json().await?;
+        let res: HexU128 = self.send(&req).await?;
 
-        let trimmed = res.result.trim_start_matches("0x");
-
-        Ok(u128::from_str_radix(trimmed, 16)?)
+        Ok(res.0)
     }
 
     pub async fn get_balances(&self, address: &QkcAddress) -> Result<Balances> {
         let req = Request::new(Method::GetBalances, Some(vec![address.to_string()]));
 
-        let res: RpcResponse<Balances> = self.client.post(&self.url).json(&req).send().await?.json().await?;
-        
-        Ok(res.result)
+        self.send(&req).await
     }
 
     pub async fn get_account_data(&self, address: &QkcAddress) -> Result<AccountData> {
         let req = Request::new(Method::GetAccountData, Some(vec![address.to_string()]));
 
-        let res: RpcResponse<AccountData> = self.client.post(&self.url).json(&req).send().await?.json().await?;
-        
-        Ok(res.result)
+        self.send(&req).await
     }
 
     pub async fn get_blocks_mined_in_recent_256(&self, miner: QkcAddress) -> Result<u64> {
-        let mut blocks = vec![self.get_minor_block_by_height(miner.full_shard_key(), Block::Latest).await?];
-        let id = u64::from_str_radix(&blocks[0].height[2..], 16)?;
-
-        let block_ids: Vec<String> = (id-255..id).map(|i| format!("0x{}", hex::encode(&i.to_be_bytes()))).collect();
+        let mut blocks = vec![self.get_minor_block_by_height(miner.full_shard_key(), Block::Latest, false, false).await?];
+        let id = *blocks[0].height;
 
-        for id in block_ids {
-            blocks.push(self.get_minor_block_by_height(miner.full_shard_key(), Block::Id(id)).await?);
-        }
+        let heights: Vec<u64> = (id.saturating_sub(255)..id).collect();
+        blocks.extend(self.get_minor_blocks_by_height(&miner.full_shard_key(), &heights, false, false).await?);
 
-        let mined: u64 = blocks.into_iter().map(|x| x.miner.starts_with(&miner.coinbase()) as u64).sum();
+        let mined: u64 = blocks.into_iter().map(|x| (x.miner.coinbase == miner.coinbase) as u64).sum();
 
         Ok(mined)
     }
 
+    /// Stakes of `address` in the root chain staking contract that count towards PoSW,
+    /// 0 while the stakes are unlocked, see [`Qkc::get_root_locked_stakes`]
     pub async fn get_root_posw_stake(&self, address: &QkcAddress) -> Result<u128> {
-        let abi = include_bytes!("./abi.json").to_vec();
-        let contract = ethabi::Contract::load(BufReader::new(abi.as_slice()))?;
-        let address_token = ethabi::Token::Address(ethabi::ethereum_types::H160::from_slice(&address.coinbase));
-        let data = contract.function("getLockedStakes")?.encode_input(&vec![address_token])?;
-        let data = format!("0x{}", hex::encode(data));
+        let stakes = self.get_root_locked_stakes(address).await?;
+        let amount = stakes.locked_amount();
 
-        let call = Call {
-            from: address.to_string(),
-            to: "0x514b43000000000000000000000000000000000100000001".to_string(),
-            data,
-            value: "0x0".to_string(),
-            gas_price: "0x0".to_string(),
-            gas: "0xf4240".to_string(),
-            gas_token_id: "0x8bb0".to_string(),
-            transfer_token_id: "0x8bb0".to_string()
-        };
+        if amount.bits() > 128 {
+            return Err(Error::InvalidResponse(format!("locked stakes {amount}")));
+        }
+
+        Ok(amount.as_u128())
+    }
 
+    /// Executes `call` without creating a transaction and returns the output data
+    pub async fn call(&self, call: Call, block: Block) -> Result<Bytes> {
         let req = CallRequest {
             jsonrpc: "2.0".to_string(),
-            params: (call, "latest".to_string()),
+            params: (call, block.get_id()),
             method: Method::Call,
             id: 1
         };
 
-        let res: RpcResponse<String> = self.client.post(&self.url).json(&req).send().await?.json().await?;
+        self.send(&req).await
+    }
+
+    /// Calls `function` of `abi` on `contract` with the ABI encoded `tokens` and decodes its outputs.
+    /// The call is free (gas price 0, [`CALL_GAS`] gas, QKC as gas and transfer token) rather than relying on node defaults.
+    /// Returns no tokens if the node returned no data, e.g. because there is no contract at the address
+    pub async fn call_function(&self, contract: &QkcAddress, abi: &Contract, function: &str, tokens: &[Token], block: Block) -> Result<Vec<Token>> {
+        let function = abi.function(function)?;
+        let call = Call {
+            gas_price: Some(U256::zero()),
+            gas: Some(CALL_GAS.into()),
+            value: Some(U256::zero()),
+            gas_token_id: Some(QKC_TOKEN_ID.into()),
+            transfer_token_id: Some(QKC_TOKEN_ID.into()),
+            ..Call::new(*contract, function.encode_input(tokens)?)
+        };
 
-        if res.result == "0x".to_string() {
-            Ok(0u128)
-        } else {
-            Ok(u128::from_str_radix(&res.result[2..66], 16)?)
+        let res = self.call(call, block).await?;
+
+        if res.is_empty() {
+            return Ok(Vec::new());
         }
+
+        Ok(function.decode_output(&res)?)
     }
 
-    pub async fn get_root_block_by_height(&self, block: Block) -> Result<RootBlockData> {
-        let req;
-        if let Block::Id(id) = block {
-            req = Request::new(Method::GetRootBlockByHeight, Some(vec![id]));
-        } else {
-            req = Request::new(Method::GetRootBlockByHeight, None);
-        }
+    pub fn transport(&self) -> &Arc<dyn Transport> {
+        &self.transport
+    }
+
+    fn ws(&self) -> Result<&WsClient> {
+        self.transport.ws().ok_or(Error::SubscriptionsUnsupported)
+    }
+
+    /// Subscribes to the notifications of type `kind` (e.g. `newHeads`, `logs`) in the shard of `full_shard_key`,
+    /// `params` are passed on for subscription types that take them
+    pub async fn subscribe<T: DeserializeOwned>(&self, kind: &str, full_shard_key: &str, params: Option<serde_json::Value>) -> Result<Subscription<T>> {
+        let mut req = vec![kind.into(), full_shard_key.into()];
+        req.extend(params);
+
+        self.ws()?.subscribe(req).await
+    }
+
+    /// Headers of the new minor blocks of the shard of `full_shard_key`
+    pub async fn subscribe_new_heads(&self, full_shard_key: &str) -> Result<Subscription<MinorBlockHeader>> {
+        self.subscribe("newHeads", full_shard_key, None).await
+    }
+
+    /// Logs matching `filter` in new minor blocks of the shard of `full_shard_key`
+    pub async fn subscribe_logs(&self, filter: &LogFilter, full_shard_key: &str) -> Result<Subscription<Log>> {
+        self.subscribe("logs", full_shard_key, Some(serde_json::to_value(filter)?)).await
+    }
+
+    /// Cancels the subscription `id`, returns `false` if the node did not know it
+    pub async fn unsubscribe(&self, id: &str) -> Result<bool> {
+        self.ws()?.unsubscribe(id).await
+    }
+
+    /// Sends an RLP encoded signed transaction, returns the transaction id
+    pub async fn send_raw_transaction(&self, raw: &[u8]) -> Result<String> {
+        let req = Request::new(Method::SendRawTransaction, Some(vec![Bytes(raw.to_vec())]));
+
+        self.send(&req).await
+    }
+
+    /// Sends a signed transaction as JSON object, returns the transaction id
+    pub async fn send_transaction(&self, tx: &SignedTransaction) -> Result<String> {
+        let req = Request::new(Method::SendTransaction, Some(vec![tx.to_request()]));
+
+        self.send(&req).await
+    }
+
+    /// `need_extra_info` makes the node include the PoSW info of the block's miner, see [`PoswInfo`]
+    pub async fn get_root_block_by_height(&self, block: Block, need_extra_info: bool) -> Result<RootBlockData> {
+        let req = Request::<serde_json::Value>::new(Method::GetRootBlockByHeight, Some(vec![block.get_id().into(), need_extra_info.into()]));
+
+        self.send(&req).await
+    }
+
+    /// `include_transactions` returns the full [`Transaction`]s instead of their ids,
+    /// `need_extra_info` makes the node include the PoSW info of the block's miner, see [`PoswInfo`]
+    pub async fn get_minor_block_by_height(&self, full_shard_key: String, block: Block, include_transactions: bool, need_extra_info: bool) -> Result<MinorBlockData> {
+        let req = MinorBlockDataRequest::new(Method::GetMinorBlockByHeight, (full_shard_key, block.get_id(), include_transactions, need_extra_info));
+
+        self.send(&req).await
+    }
+
+    /// Root block by its hash, see [`Qkc::get_root_block_by_height`]
+    pub async fn get_root_block_by_id(&self, id: &str, need_extra_info: bool) -> Result<RootBlockData> {
+        let req = Request::<serde_json::Value>::new(Method::GetRootBlockById, Some(vec![id.into(), need_extra_info.into()]));
 
-        let res: RpcResponse<RootBlockData> = self.client.post(&self.url).json(&req).send().await?.json().await?;
-        
-        Ok(res.result)
+        self.send(&req).await
     }
 
-    pub async fn get_minor_block_by_height(&self, full_shard_key: String, block: Block) -> Result<MinorBlockData> {
-        let req = MinorBlockDataRequest::new(Method::GetMinorBlockByHeight, (full_shard_key, block.get_id(), false));
+    /// Minor block by its id (hash followed by the full shard id), see [`Qkc::get_minor_block_by_height`]
+    pub async fn get_minor_block_by_id(&self, id: &str, include_transactions: bool, need_extra_info: bool) -> Result<MinorBlockData> {
+        let req = Request::<serde_json::Value>::new(Method::GetMinorBlockById, Some(vec![id.into(), include_transactions.into(), need_extra_info.into()]));
 
-        let res: RpcResponse<MinorBlockData> = self.client.post(&self.url).json(&req).send().await?.json().await?;
+        self.send(&req).await
+    }
+
+    /// Transaction by its id (hash followed by the sender's full shard key),
+    /// [`Error::EmptyResult`] if the node does not know it
+    pub async fn get_transaction_by_id(&self, id: &str) -> Result<Transaction> {
+        let req = Request::new(Method::GetTransactionById, Some(vec![id]));
+
+        self.send(&req).await
+    }
+
+    /// Receipt of the transaction `id`, [`Error::EmptyResult`] until the transaction is included in a block
+    pub async fn get_transaction_receipt(&self, id: &str) -> Result<TransactionReceipt> {
+        let req = Request::new(Method::GetTransactionReceipt, Some(vec![id]));
+
+        self.send(&req).await
+    }
+
+    /// Code of the contract at `address`, empty for accounts without code
+    pub async fn get_code(&self, address: &QkcAddress, block: Block) -> Result<Bytes> {
+        let req = Request::<serde_json::Value>::new(Method::GetCode, Some(vec![address.to_string().into(), block.get_id().into()]));
+
+        self.send(&req).await
+    }
+
+    /// Storage slot `key` of the contract at `address`
+    pub async fn get_storage_at(&self, address: &QkcAddress, key: U256, block: Block) -> Result<H256> {
+        let req = Request::<serde_json::Value>::new(Method::GetStorageAt, Some(vec![
+            address.to_string().into(),
+            serde_json::to_value(key)?,
+            block.get_id().into()
+        ]));
+
+        self.send(&req).await
+    }
+
+    /// Gas price suggested by the node for the shard of `full_shard_key`, in `token_id` or QKC if `None`
+    pub async fn gas_price(&self, full_shard_key: &str, token_id: Option<u64>) -> Result<U256> {
+        let mut params = vec![full_shard_key.into()];
+        params.extend(token_id.map(|id| serde_json::Value::from(format!("{id:#x}"))));
+
+        let req = Request::new(Method::GasPrice, Some(params));
+
+        self.send(&req).await
+    }
+
+    /// Gas `call` would use if it was sent as transaction
+    pub async fn estimate_gas(&self, call: &Call) -> Result<u64> {
+        let req = Request::new(Method::EstimateGas, Some(vec![call]));
+
+        let res: HexU64 = self.send(&req).await?;
+
+        Ok(res.0)
+    }
+
+    /// Every shard of the network
+    pub async fn get_full_shard_ids(&self) -> Result<Vec<Branch>> {
+        let req = Request::<String>::new(Method::GetFullShardIds, None);
+
+        let ids: Vec<HexU64> = self.send(&req).await?;
+
+        ids.into_iter()
+            .map(|id| u32::try_from(id.0).ok().and_then(|id| Branch::from_full_shard_id(id).ok()).ok_or_else(|| Error::InvalidResponse(format!("full shard id {id}"))))
+            .collect()
+    }
+
+    /// Block template to mine on the shard of `full_shard_key` or on the root chain if `None`, with
+    /// `coinbase` as miner instead of the node's default. [`Error::EmptyResult`] if the node has no work, e.g. while syncing
+    pub async fn get_work(&self, full_shard_key: Option<&str>, coinbase: Option<H160>) -> Result<Work> {
+        let mut params = vec![full_shard_key.into()];
+        params.extend(coinbase.map(|coinbase| serde_json::Value::from(format!("{coinbase:?}"))));
+
+        let req = Request::new(Method::GetWork, Some(params));
+
+        self.send(&req).await
+    }
+
+    /// Submits the solution `nonce` and `mixhash` for the work `header_hash` of [`Qkc::get_work`],
+    /// root chain blocks may need the guardian's `signature`. Returns `false` if the node rejected it
+    pub async fn submit_work(&self, full_shard_key: Option<&str>, header_hash: H256, nonce: u64, mixhash: H256, signature: Option<&[u8]>) -> Result<bool> {
+        let mut params = vec![
+            full_shard_key.into(),
+            serde_json::to_value(header_hash)?,
+            format!("{nonce:#x}").into(),
+            serde_json::to_value(mixhash)?
+        ];
+        params.extend(signature.map(|signature| serde_json::Value::from(Bytes(signature.to_vec()).to_string())));
+
+        let req = Request::new(Method::SubmitWork, Some(params));
+
+        self.send(&req).await
+    }
+
+    /// PoSW info of `address` in its primary shard as reported by the node,
+    /// `None` if the node does not report mined and mineable blocks with the account data
+    pub async fn posw_info(&self, address: &QkcAddress) -> Result<Option<AccountPoswInfo>> {
+        let account = self.get_account_data(address).await?.primary;
+
+        let (Some(mined), Some(mineable)) = (account.mined_blocks, account.posw_mineable_blocks) else {
+            return Ok(None);
+        };
+
+        Ok(Some(AccountPoswInfo {
+            mined_blocks: mined.0,
+            mineable_blocks: mineable.0
+        }))
+    }
+
+    /// Batched [`Qkc::get_root_block_by_height`], blocks are returned in the order of `heights`
+    pub async fn get_root_blocks_by_height(&self, heights: &[u64], need_extra_info: bool) -> Result<Vec<RootBlockData>> {
+        let calls: Vec<BatchCall> = heights.iter()
+            .map(|height| BatchCall::new(Method::GetRootBlockByHeight, Some(vec![format!("{height:#x}").into(), need_extra_info.into()])))
+            .collect();
+
+        self.batch(&calls).await?.into_iter().collect()
+    }
+
+    /// Batched [`Qkc::get_minor_block_by_height`], blocks are returned in the order of `heights`
+    pub async fn get_minor_blocks_by_height(&self, full_shard_key: &str, heights: &[u64], include_transactions: bool, need_extra_info: bool) -> Result<Vec<MinorBlockData>> {
+        let calls: Vec<BatchCall> = heights.iter()
+            .map(|height| BatchCall::new(Method::GetMinorBlockByHeight, Some(vec![
+                full_shard_key.into(),
+                format!("{height:#x}").into(),
+                include_transactions.into(),
+                need_extra_info.into()
+            ])))
+            .collect();
 
-        Ok(res.result)
+        self.batch(&calls).await?.into_iter().collect()
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Parameters of the `call` RPC, unset fields are filled in by the node
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Call {
-    pub from: String,
-    pub to: String,
-    pub gas_price: String,
-    pub gas: String,
-    pub data: String,
-    pub value: String,
-    pub gas_token_id: String,
-    pub transfer_token_id: String
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<QkcAddress>,
+    pub to: QkcAddress,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<HexU64>,
+    pub data: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_token_id: Option<HexU64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_token_id: Option<HexU64>
+}
+
+impl Call {
+    pub fn new(to: QkcAddress, data: Vec<u8>) -> Self {
+        Self {
+            from: None,
+            to,
+            gas_price: None,
+            gas: None,
+            data: Bytes(data),
+            value: None,
+            gas_token_id: None,
+            transfer_token_id: None
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 struct CallRequest {
     pub jsonrpc: String,
     pub method: Method,
-    pub params: (Call, String),
+    pub params: (Call, Option<String>),
     pub id: usize
 }
 
@@ -155,82 +439,303 @@ impl Block {
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct RpcResponse<T> {
+pub(crate) struct RpcResponse<T> {
     jsonrpc: String,
-    pub(crate) result: T,
-    id: usize
+    #[serde(default = "Option::default")]
+    pub(crate) result: Option<T>,
+    #[serde(default)]
+    pub(crate) error: Option<RpcError>,
+    #[serde(default)]
+    id: Option<usize>
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BatchResponse {
+    Batch(Vec<RpcResponse<serde_json::Value>>),
+    Single(RpcResponse<serde_json::Value>)
+}
+
+impl<T> RpcResponse<T> {
+    pub(crate) fn into_result(self) -> Result<T> {
+        match (self.result, self.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(Error::EmptyResult)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
 pub struct RootBlockData {
     pub id: String,
-    pub hash: String,
-    pub height: String,
+    pub hash: H256,
+    pub height: HexU64,
     pub id_prev_block: String,
-    pub hash_prev_block: String,
-    pub nonce: String,
-    pub hash_merkle_root: String,
-    pub miner: String,
+    pub hash_prev_block: H256,
+    pub nonce: HexU64,
+    pub hash_merkle_root: H256,
+    pub miner: QkcAddress,
     pub coinbase: Vec<Balance>,
-    pub difficulty: String,
-    pub timestamp: String,
-    pub size: String
+    pub difficulty: HexU128,
+    pub timestamp: HexU64,
+    pub size: HexU64,
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub posw_info: Option<PoswInfo>
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
 pub struct MinorBlockData {
     pub id: String,
-    pub height: String,
-    pub hash: String,
-    pub full_shard_id: String,
-    pub chain_id: String,
-    pub shard_id: String,
-    pub hash_prev_minor_block: String,
+    pub height: HexU64,
+    pub hash: H256,
+    pub full_shard_id: HexU64,
+    pub chain_id: HexU64,
+    pub shard_id: HexU64,
+    pub hash_prev_minor_block: H256,
     pub id_prev_minor_block: String,
-    pub hash_prev_root_block: String,
-    pub nonce: String,
-    pub hash_merkle_root: String,
-    pub hash_evm_state_root: String,
-    pub miner: String,
+    pub hash_prev_root_block: H256,
+    pub nonce: HexU64,
+    pub hash_merkle_root: H256,
+    pub hash_evm_state_root: H256,
+    pub miner: QkcAddress,
     pub coinbase: Vec<Balance>,
-    pub difficulty: String,
-    pub extra_data: String,
-    pub gas_limit: String,
-    pub gas_used: String,
-    pub timestamp: String,
-    pub size: String,
-    pub transactions: Vec<()>,
+    pub difficulty: HexU128,
+    pub extra_data: Bytes,
+    pub gas_limit: HexU64,
+    pub gas_used: HexU64,
+    pub timestamp: HexU64,
+    pub size: HexU64,
+    pub transactions: Vec<BlockTransaction>,
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub posw_info: Option<PoswInfo>
+}
+
+/// Minor block header as pushed by `newHeads` subscriptions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all="camelCase")]
+pub struct MinorBlockHeader {
+    pub id: String,
+    pub height: HexU64,
+    pub hash: H256,
+    pub full_shard_id: HexU64,
+    pub chain_id: HexU64,
+    pub shard_id: HexU64,
+    pub hash_prev_minor_block: H256,
+    pub id_prev_minor_block: String,
+    pub hash_prev_root_block: H256,
+    pub nonce: HexU64,
+    pub miner: QkcAddress,
+    pub difficulty: HexU128,
+    pub timestamp: HexU64
+}
+
+impl MinorBlockData {
+    /// Full transactions of the block, empty unless requested with `include_transactions`
+    pub fn full_transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.transactions.iter().filter_map(|tx| match tx {
+            BlockTransaction::Full(tx) => Some(tx.as_ref()),
+            BlockTransaction::Id(_) => None
+        })
+    }
+}
+
+/// Transaction of a minor block, either its id or the full transaction if requested with `include_transactions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BlockTransaction {
+    Id(String),
+    Full(Box<Transaction>)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all="camelCase")]
+pub struct Transaction {
+    pub id: String,
+    pub hash: H256,
+    pub nonce: HexU64,
+    pub timestamp: HexU64,
+    pub full_shard_key: HexU64,
+    pub chain_id: HexU64,
+    pub shard_id: HexU64,
+    pub block_id: String,
+    pub block_height: HexU64,
+    pub transaction_index: HexU64,
+    pub from: H160,
+    /// `None` for contract creations
+    #[serde(with = "optional_address")]
+    pub to: Option<H160>,
+    pub from_full_shard_key: HexU64,
+    pub to_full_shard_key: HexU64,
+    pub value: U256,
+    pub gas_price: U256,
+    pub gas: HexU64,
+    pub data: Bytes,
+    pub network_id: HexU64,
+    pub transfer_token_id: HexU64,
+    pub gas_token_id: HexU64,
+    pub transfer_token_str: String,
+    pub gas_token_str: String,
+    pub r: U256,
+    pub s: U256,
+    pub v: U256
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all="camelCase")]
+pub struct TransactionReceipt {
+    /// `None` for receipts without transaction, e.g. of cross shard deposits
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    #[serde(default)]
+    pub transaction_hash: Option<H256>,
+    pub transaction_index: HexU64,
+    pub block_id: String,
+    pub block_hash: H256,
+    pub block_height: HexU64,
+    pub cumulative_gas_used: HexU64,
+    pub gas_used: HexU64,
+    /// 1 if the transaction succeeded, 0 if it reverted
+    pub status: HexU64,
+    /// Address of the contract created by the transaction
+    #[serde(default)]
+    pub contract_address: Option<QkcAddress>,
+    pub logs: Vec<Log>,
+    pub timestamp: HexU64
+}
+
+impl TransactionReceipt {
+    pub fn succeeded(&self) -> bool {
+        *self.status == 1
+    }
+}
+
+/// Mining work returned by `getWork`, sent by the node as `[headerHash, height, difficulty, divider?]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Work {
+    pub header_hash: H256,
+    pub height: u64,
+    pub difficulty: u128,
+    /// Difficulty divider the node applies if the coinbase has PoSW allowances left
+    pub posw_divider: Option<u128>
+}
+
+impl Work {
+    /// Difficulty the solution has to meet, after the PoSW divider if any
+    pub fn effective_difficulty(&self) -> u128 {
+        self.difficulty / self.posw_divider.unwrap_or(1).max(1)
+    }
+}
+
+impl Serialize for Work {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut work = vec![format!("{:?}", self.header_hash), HexU64(self.height).to_string(), HexU128(self.difficulty).to_string()];
+        work.extend(self.posw_divider.map(|divider| HexU128(divider).to_string()));
+
+        work.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Work {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let work = Vec::<String>::deserialize(deserializer)?;
+        let [header_hash, height, difficulty, divider @ ..] = work.as_slice() else {
+            return Err(D::Error::invalid_length(work.len(), &"3 or 4 elements"));
+        };
+
+        Ok(Self {
+            header_hash: header_hash.parse().map_err(|_| D::Error::custom(format!("invalid header hash {header_hash}")))?,
+            height: *height.parse::<HexU64>().map_err(D::Error::custom)?,
+            difficulty: *difficulty.parse::<HexU128>().map_err(D::Error::custom)?,
+            posw_divider: match divider {
+                [] => None,
+                [divider] => Some(*divider.parse::<HexU128>().map_err(D::Error::custom)?),
+                _ => return Err(D::Error::invalid_length(work.len(), &"3 or 4 elements"))
+            }
+        })
+    }
+}
+
+/// Contract creations have an empty `to` (`"0x"`)
+mod optional_address {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    use crate::types::H160;
+
+    pub fn serialize<S: Serializer>(address: &Option<H160>, serializer: S) -> Result<S::Ok, S::Error> {
+        match address {
+            Some(address) => serializer.serialize_str(&format!("{address:?}")),
+            None => serializer.serialize_str("0x")
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<H160>, D::Error> {
+        match Option::<String>::deserialize(deserializer)?.as_deref() {
+            None | Some("") | Some("0x") => Ok(None),
+            Some(address) => address.parse().map(Some).map_err(D::Error::custom)
+        }
+    }
+}
+
+/// PoSW info of a block's miner, only present on blocks requested with `need_extra_info`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all="camelCase")]
+pub struct PoswInfo {
+    pub effective_difficulty: HexU128,
+    pub posw_mineable_blocks: HexU64,
+    pub posw_mined_blocks: HexU64,
+    pub staking_applied: bool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountPoswInfo {
+    pub mined_blocks: u64,
+    pub mineable_blocks: u64
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
 pub struct NetworkInfo {
-    pub network_id: String,
-    pub chain_size: String,
-    pub shard_sizes: Vec<String>,
+    pub network_id: HexU64,
+    pub chain_size: HexU64,
+    pub shard_sizes: Vec<HexU64>,
     pub syncing: bool,
     pub mining: bool,
     pub shard_server_count: usize
 }
 
+impl NetworkInfo {
+    /// Shard sizes of the network, checked against its chain size
+    pub fn shard_layout(&self) -> Result<ShardLayout> {
+        if self.shard_sizes.len() as u64 != *self.chain_size {
+            return Err(Error::InvalidResponse(format!("{} shard sizes for chain size {}", self.shard_sizes.len(), self.chain_size)));
+        }
+
+        let shard_sizes = self.shard_sizes.iter().map(|size| size.0).collect::<Vec<_>>();
+        ShardLayout::new(&shard_sizes).map_err(|e| Error::InvalidResponse(e.to_string()))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
 pub struct Balances {
-    pub branch: String,
-    pub full_shard_id: String,
-    pub shard_id: String,
-    pub chain_id: String,
+    pub branch: HexU64,
+    pub full_shard_id: HexU64,
+    pub shard_id: HexU64,
+    pub chain_id: HexU64,
     pub balances: Vec<Balance>
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
 pub struct Balance {
-    pub token_id: String,
+    pub token_id: HexU64,
     pub token_str: String,
-    pub balance: String
+    pub balance: U256
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -243,24 +748,28 @@ pub struct AccountData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
 pub struct AccountShardData {
-    pub full_shard_id: String,
-    pub shard_id: String,
-    pub chain_id: String,
+    pub full_shard_id: HexU64,
+    pub shard_id: HexU64,
+    pub chain_id: HexU64,
     pub balances: Vec<Balance>,
-    pub transaction_count: String,
+    pub transaction_count: HexU64,
     pub is_contract: bool,
+    #[serde(default)]
+    pub mined_blocks: Option<HexU64>,
+    #[serde(default)]
+    pub posw_mineable_blocks: Option<HexU64>,
 }
 
 #[derive(Debug, Serialize)]
 struct MinorBlockDataRequest {
     jsonrpc: String,
     method: Method,
-    params: (String, Option<String>, bool),
+    params: (String, Option<String>, bool, bool),
     id: usize
 }
 
 impl MinorBlockDataRequest {
-    pub(crate) fn new(method: Method, params: (String, Option<String>, bool)) -> Self {
+    pub(crate) fn new(method: Method, params: (String, Option<String>, bool, bool)) -> Self {
 
         Self {
             jsonrpc: "2.0".into(),
@@ -272,7 +781,7 @@ impl MinorBlockDataRequest {
 }
 
 #[derive(Debug, Serialize)]
-struct Request<T: Serialize> {
+pub(crate) struct Request<T: Serialize> {
     jsonrpc: String,
     method: Method,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -292,14 +801,283 @@ impl<T> Request<T> where T: Serialize {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A single call of a JSON-RPC batch, see [`Qkc::batch`]
+#[derive(Debug, Clone)]
+pub struct BatchCall {
+    method: Method,
+    params: Option<Vec<serde_json::Value>>
+}
+
+impl BatchCall {
+    pub fn new(method: Method, params: Option<Vec<serde_json::Value>>) -> Self {
+        Self {
+            method,
+            params
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all="camelCase")]
-enum Method {
+pub enum Method {
     NetworkInfo,
     GetTransactionCount,
     GetBalances,
     GetAccountData,
     GetMinorBlockByHeight,
     GetRootBlockByHeight,
-    Call
-}
\ No newline at end of file
+    Call,
+    SendRawTransaction,
+    SendTransaction,
+    GetLogs,
+    Subscribe,
+    Unsubscribe,
+    GetRootBlockById,
+    GetMinorBlockById,
+    GetTransactionById,
+    GetTransactionReceipt,
+    GetCode,
+    GetStorageAt,
+    GasPrice,
+    EstimateGas,
+    GetFullShardIds,
+    GetWork,
+    SubmitWork
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rpc_error_response_test() {
+        let body = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"},"id":1}"#;
+        let res: RpcResponse<NetworkInfo> = serde_json::from_str(body).unwrap();
+
+        match res.into_result() {
+            Err(Error::Rpc(e)) => {
+                assert_eq!(e.code, -32602);
+                assert_eq!(e.message, "Invalid params");
+            },
+            other => panic!("unexpected {other:?}")
+        }
+
+        let body = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+        let res: RpcResponse<NetworkInfo> = serde_json::from_str(body).unwrap();
+
+        assert!(matches!(res.into_result(), Err(Error::EmptyResult)));
+    }
+
+    #[test]
+    fn posw_info_test() {
+        let hash = format!("0x{}", "11".repeat(32));
+        let block = serde_json::json!({
+            "id": hash, "hash": hash, "height": "0x10", "idPrevBlock": hash, "hashPrevBlock": hash,
+            "nonce": "0x0", "hashMerkleRoot": hash, "miner": "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000",
+            "coinbase": [{"tokenId": "0x8bb0", "tokenStr": "QKC", "balance": "0x0"}],
+            "difficulty": "0x3e8", "timestamp": "0x5", "size": "0x6"
+        });
+
+        let res: RootBlockData = serde_json::from_value(block.clone()).unwrap();
+        assert!(res.posw_info.is_none());
+        assert_eq!(*res.height, 16);
+        assert_eq!(res.miner.coinbase(), "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b");
+
+        let mut invalid = block.clone();
+        invalid["height"] = "0x".into();
+        assert!(serde_json::from_value::<RootBlockData>(invalid).is_err());
+
+        let mut block = block;
+        block["effectiveDifficulty"] = "0x32".into();
+        block["poswMineableBlocks"] = "0x10".into();
+        block["poswMinedBlocks"] = "0x2".into();
+        block["stakingApplied"] = true.into();
+
+        let res: RootBlockData = serde_json::from_value(block).unwrap();
+        let posw = res.posw_info.unwrap();
+        assert_eq!(*posw.effective_difficulty, 0x32);
+        assert!(posw.staking_applied);
+    }
+
+    #[test]
+    fn transaction_test() {
+        let hash = format!("0x{}", "22".repeat(32));
+        let tx = serde_json::json!({
+            "id": format!("{hash}00000001"), "hash": hash, "nonce": "0x1", "timestamp": "0x5f5e1000",
+            "fullShardKey": "0x00000001", "chainId": "0x0", "shardId": "0x1",
+            "blockId": format!("{hash}00000001"), "blockHeight": "0x10", "transactionIndex": "0x0",
+            "from": "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b", "to": "0x",
+            "fromFullShardKey": "0x00000001", "toFullShardKey": "0x00000001",
+            "value": "0xde0b6b3a7640000", "gasPrice": "0x3b9aca00", "gas": "0x7530", "data": "0x",
+            "networkId": "0x1", "transferTokenId": "0x8bb0", "gasTokenId": "0x8bb0",
+            "transferTokenStr": "QKC", "gasTokenStr": "QKC", "r": "0x1", "s": "0x2", "v": "0x1b"
+        });
+
+        let res: Vec<BlockTransaction> = serde_json::from_value(serde_json::json!([tx, format!("{hash}00000001")])).unwrap();
+
+        match &res[0] {
+            BlockTransaction::Full(tx) => {
+                assert!(tx.to.is_none());
+                assert_eq!(tx.value, U256::exp10(18));
+                assert_eq!(*tx.transfer_token_id, 0x8bb0);
+                assert_eq!(format!("{:?}", tx.from), "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b");
+            },
+            other => panic!("unexpected {other:?}")
+        }
+
+        assert!(matches!(&res[1], BlockTransaction::Id(_)));
+    }
+
+    #[test]
+    fn call_params_test() {
+        let contract = QkcAddress::new_full(ROOT_STAKING_CONTRACT).unwrap();
+        let mut call = Call::new(contract, vec![0xfd, 0x8c, 0x46, 0x46]);
+
+        let req = CallRequest {
+            jsonrpc: "2.0".to_string(),
+            params: (call.clone(), Block::Latest.get_id()),
+            method: Method::Call,
+            id: 1
+        };
+        assert_eq!(serde_json::to_value(&req).unwrap()["params"], serde_json::json!([
+            {"to": ROOT_STAKING_CONTRACT, "data": "0xfd8c4646"},
+            null
+        ]));
+
+        call.gas = Some(HexU64(1_000_000));
+        let req = CallRequest {
+            jsonrpc: "2.0".to_string(),
+            params: (call, Block::Id("0x10".into()).get_id()),
+            method: Method::Call,
+            id: 1
+        };
+        assert_eq!(serde_json::to_value(&req).unwrap()["params"], serde_json::json!([
+            {"to": ROOT_STAKING_CONTRACT, "data": "0xfd8c4646", "gas": "0xf4240"},
+            "0x10"
+        ]));
+    }
+
+    #[test]
+    fn work_test() {
+        let hash = format!("0x{}", "44".repeat(32));
+
+        let work: Work = serde_json::from_value(serde_json::json!([hash, "0x10", "0x3e8"])).unwrap();
+        assert_eq!(format!("{:?}", work.header_hash), hash);
+        assert_eq!((work.height, work.difficulty, work.posw_divider), (16, 1000, None));
+        assert_eq!(work.effective_difficulty(), 1000);
+
+        let work: Work = serde_json::from_value(serde_json::json!([hash, "0x10", "0x3e8", "0x14"])).unwrap();
+        assert_eq!(work.effective_difficulty(), 50);
+        assert_eq!(serde_json::to_value(work).unwrap(), serde_json::json!([hash, "0x10", "0x3e8", "0x14"]));
+
+        assert!(serde_json::from_value::<Work>(serde_json::json!([hash, "0x10"])).is_err());
+        assert!(serde_json::from_value::<Work>(serde_json::json!([hash, "0x10", "0x3e8", "0x14", "0x1"])).is_err());
+        assert!(serde_json::from_value::<Work>(serde_json::json!(["0x12", "0x10", "0x3e8"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn read_rpc_test() {
+        use crate::{QkcWeb3, transport::MockTransport};
+
+        let hash = format!("0x{}", "33".repeat(32));
+        let address = QkcAddress::new_full("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00010000").unwrap();
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(Method::GetTransactionReceipt, serde_json::json!({
+                "transactionId": format!("{hash}00010000"), "transactionHash": hash, "transactionIndex": "0x0",
+                "blockId": format!("{hash}00010001"), "blockHash": hash, "blockHeight": "0x10", "blockNumber": "0x10",
+                "cumulativeGasUsed": "0x5208", "gasUsed": "0x5208", "status": "0x1", "contractAddress": null,
+                "logs": [], "timestamp": "0x5f5e1000"
+            }))
+            .respond(Method::GetCode, "0x6080")
+            .respond(Method::GetStorageAt, format!("0x{}01", "00".repeat(31)))
+            .respond(Method::GasPrice, "0x3b9aca00")
+            .respond(Method::EstimateGas, "0x5208")
+            .respond(Method::GetFullShardIds, serde_json::json!(["0x1", "0x10001", "0x20004", "0x20007"]));
+
+        let web3 = QkcWeb3::with_transport(mock.clone(), RetryPolicy::no_retries());
+        let qkc = web3.qkc();
+
+        let receipt = qkc.get_transaction_receipt(&format!("{hash}00010000")).await.unwrap();
+        assert!(receipt.succeeded());
+        assert_eq!(*receipt.gas_used, 21_000);
+        assert!(receipt.contract_address.is_none());
+
+        assert_eq!(&*qkc.get_code(&address, Block::Latest).await.unwrap(), &[0x60, 0x80]);
+        assert_eq!(qkc.get_storage_at(&address, U256::from(2), Block::Id("0x10".into())).await.unwrap(), H256::from_low_u64_be(1));
+        assert_eq!(qkc.gas_price(&address.full_shard_key(), None).await.unwrap(), U256::from(1_000_000_000));
+        assert_eq!(qkc.estimate_gas(&Call::new(address, vec![])).await.unwrap(), 21_000);
+
+        let branches = qkc.get_full_shard_ids().await.unwrap();
+        assert_eq!(branches.iter().map(|b| (b.chain_id(), b.shard_id())).collect::<Vec<_>>(), vec![(0, 0), (1, 0), (2, 0), (2, 3)]);
+
+        let params = mock.requests().into_iter().map(|req| (req["method"].as_str().unwrap().to_string(), req["params"].clone())).collect::<Vec<_>>();
+        assert_eq!(params[1], ("getCode".into(), serde_json::json!([address.to_string(), null])));
+        assert_eq!(params[2], ("getStorageAt".into(), serde_json::json!([address.to_string(), "0x2", "0x10"])));
+        assert_eq!(params[3], ("gasPrice".into(), serde_json::json!(["0x00010000"])));
+        assert_eq!(params[4].1, serde_json::json!([{"to": address.to_string(), "data": "0x"}]));
+
+        assert!(matches!(qkc.get_transaction_by_id(&hash).await, Err(Error::Rpc(e)) if e.code == -32601));
+        assert_eq!(mock.requests().last().unwrap()["method"], "getTransactionById");
+    }
+
+    #[tokio::test]
+    async fn send_transaction_test() {
+        use crate::{QkcWeb3, transport::MockTransport, types::EvmTransaction, wallet::Wallet};
+
+        let wallet = Wallet::from_hex("4646464646464646464646464646464646464646464646464646464646464646").unwrap();
+        let signed = wallet.sign_transaction(EvmTransaction {
+            nonce: 9,
+            to: Some(H160::repeat_byte(0x35)),
+            value: U256::exp10(18),
+            from_full_shard_key: 0x00010000,
+            to_full_shard_key: 0x00010000,
+            ..Default::default()
+        }).unwrap();
+
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(Method::SendRawTransaction, signed.id())
+            .respond(Method::SendTransaction, signed.id());
+        let web3 = QkcWeb3::with_transport(mock.clone(), RetryPolicy::no_retries());
+
+        assert_eq!(web3.qkc().send_raw_transaction(&signed.raw()).await.unwrap(), signed.id());
+        assert_eq!(web3.qkc().send_transaction(&signed).await.unwrap(), signed.id());
+
+        let requests = mock.requests();
+        assert_eq!(requests[0]["method"], "sendRawTransaction");
+        assert_eq!(requests[0]["params"], serde_json::json!([format!("0x{}", hex::encode(signed.raw()))]));
+        assert_eq!(requests[1]["method"], "sendTransaction");
+        assert_eq!(requests[1]["params"], serde_json::json!([{
+            "nonce": "0x9", "gasPrice": "0x0", "gas": "0x5208", "to": format!("0x{}", "35".repeat(20)),
+            "value": "0xde0b6b3a7640000", "data": "0x", "networkId": "0x1",
+            "fromFullShardKey": "0x00010000", "toFullShardKey": "0x00010000",
+            "gasTokenId": "0x8bb0", "transferTokenId": "0x8bb0",
+            "v": format!("{:#x}", signed.v), "r": format!("{:#x}", signed.r), "s": format!("{:#x}", signed.s)
+        }]));
+    }
+
+    #[tokio::test]
+    async fn call_function_test() {
+        use crate::{QkcWeb3, transport::MockTransport};
+
+        let contract = QkcAddress::new_full(ROOT_STAKING_CONTRACT).unwrap();
+        let abi = staking_contract().unwrap();
+        let output = ethabi::encode(&[Token::Bool(false), Token::Uint(U256::zero()), Token::Uint(U256::exp10(18)), Token::Address(H160::zero())]);
+
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(Method::Call, Bytes(output));
+        let web3 = QkcWeb3::with_transport(mock.clone(), RetryPolicy::no_retries());
+
+        let owner = H160::repeat_byte(0x11);
+        let tokens = web3.qkc().call_function(&contract, &abi, "stakes", &[Token::Address(owner)], Block::Latest).await.unwrap();
+        assert_eq!(tokens[2], Token::Uint(U256::exp10(18)));
+
+        let data = abi.function("stakes").unwrap().encode_input(&[Token::Address(owner)]).unwrap();
+        assert_eq!(mock.requests()[0]["params"], serde_json::json!([
+            {
+                "to": ROOT_STAKING_CONTRACT, "data": Bytes(data).to_string(), "value": "0x0", "gasPrice": "0x0",
+                "gas": "0xf4240", "gasTokenId": "0x8bb0", "transferTokenId": "0x8bb0"
+            },
+            null
+        ]));
+    }
+}
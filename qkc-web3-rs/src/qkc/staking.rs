@@ -0,0 +1,262 @@
+use std::io::BufReader;
+
+use ethabi::{Contract, Token};
+
+use crate::{error::{Error, Result}, types::{EvmTransaction, QkcAddress, H160, U256}};
+
+use super::{Block, Qkc};
+
+/// Root chain PoSW staking contract, deployed in chain 0 shard 0
+pub const ROOT_STAKING_CONTRACT: &str = "0x514b43000000000000000000000000000000000100000001";
+
+/// Gas limit of the transactions built by [`Qkc::root_staking_transaction`]
+pub const STAKING_GAS: u64 = 100_000;
+
+/// Loads the ABI of the root chain staking contract
+pub fn staking_contract() -> Result<Contract> {
+    let abi = include_bytes!("./abi.json");
+
+    Ok(Contract::load(BufReader::new(abi.as_slice()))?)
+}
+
+fn contract_address() -> QkcAddress {
+    QkcAddress::new_full(ROOT_STAKING_CONTRACT).expect("valid contract address")
+}
+
+/// State changing calls of the root chain staking contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StakingCall {
+    /// Locks the sent amount as additional stakes, `lock()`
+    AddStakes(U256),
+    /// Starts the unlock period after which the stakes can be withdrawn, `unlock()`
+    RequestWithdraw,
+    /// Withdraws part of the unlocked stakes, `withdraw(uint256)`
+    Withdraw(U256),
+    /// Withdraws all unlocked stakes, `withdrawAll()`
+    WithdrawAll,
+    /// Sets the address whose signature is accepted for root blocks mined with the stakes, `setSigner(address)`
+    SetSigner(H160)
+}
+
+impl StakingCall {
+    pub fn function_name(&self) -> &'static str {
+        match self {
+            StakingCall::AddStakes(_) => "lock",
+            StakingCall::RequestWithdraw => "unlock",
+            StakingCall::Withdraw(_) => "withdraw",
+            StakingCall::WithdrawAll => "withdrawAll",
+            StakingCall::SetSigner(_) => "setSigner"
+        }
+    }
+
+    /// QKC sent along with the call
+    pub fn value(&self) -> U256 {
+        match self {
+            StakingCall::AddStakes(amount) => *amount,
+            _ => U256::zero()
+        }
+    }
+
+    /// ABI encoded call data
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let tokens = match self {
+            StakingCall::Withdraw(amount) => vec![Token::Uint(*amount)],
+            StakingCall::SetSigner(signer) => vec![Token::Address(*signer)],
+            _ => vec![]
+        };
+
+        Ok(staking_contract()?.function(self.function_name())?.encode_input(&tokens)?)
+    }
+}
+
+/// Stakes of an account in the root chain staking contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockedStakes {
+    pub owner: H160,
+    /// Set once a withdraw was requested, the stakes no longer count towards PoSW
+    pub unlocked: bool,
+    /// Unix timestamp from which unlocked stakes can be withdrawn
+    pub withdrawable_timestamp: u64,
+    pub amount: U256,
+    signer: H160
+}
+
+impl LockedStakes {
+    fn from_tokens(owner: H160, tokens: Vec<Token>) -> Result<Self> {
+        match tokens.as_slice() {
+            [Token::Bool(unlocked), Token::Uint(withdrawable_timestamp), Token::Uint(amount), Token::Address(signer)] => Ok(Self {
+                owner,
+                unlocked: *unlocked,
+                withdrawable_timestamp: (*withdrawable_timestamp).try_into()
+                    .map_err(|_| Error::InvalidResponse(format!("withdrawable timestamp {withdrawable_timestamp}")))?,
+                amount: *amount,
+                signer: *signer
+            }),
+            _ => Err(Error::InvalidResponse(format!("stakes {tokens:?}")))
+        }
+    }
+
+    /// Stakes that count towards PoSW, 0 while unlocked
+    pub fn locked_amount(&self) -> U256 {
+        if self.unlocked {
+            U256::zero()
+        } else {
+            self.amount
+        }
+    }
+
+    /// Address that signs root blocks mined with the stakes, the owner unless a signer was set
+    pub fn signer(&self) -> H160 {
+        if self.signer.is_zero() {
+            self.owner
+        } else {
+            self.signer
+        }
+    }
+
+    /// Whether the stakes are unlocked but can not be withdrawn yet at unix timestamp `now`
+    pub fn in_unlock_period(&self, now: u64) -> bool {
+        self.unlocked && now < self.withdrawable_timestamp
+    }
+}
+
+impl Qkc {
+    /// Builds the unsigned transaction of `call` sent by `owner`, filling in the owner's
+    /// nonce in the staking contract's shard and the node's network id. The shard key of `owner` is ignored,
+    /// the transaction is always sent from the contract's shard
+    pub async fn root_staking_transaction(&self, owner: &QkcAddress, call: StakingCall, gas_price: U256) -> Result<EvmTransaction> {
+        let contract = contract_address();
+        let full_shard_key = contract.full_shard_key_u32();
+        let sender = QkcAddress::from_parts(owner.coinbase, full_shard_key);
+
+        let (nonce, network_info) = futures::try_join!(self.get_transaction_count(&sender), self.network_info())?;
+        let network_id = u32::try_from(*network_info.network_id)
+            .map_err(|_| Error::InvalidResponse(format!("network id {}", network_info.network_id)))?;
+
+        Ok(EvmTransaction {
+            nonce: nonce as u64,
+            gas_price,
+            gas: STAKING_GAS,
+            to: Some(contract.coinbase),
+            value: call.value(),
+            data: call.encode()?,
+            network_id,
+            from_full_shard_key: full_shard_key,
+            to_full_shard_key: full_shard_key,
+            ..Default::default()
+        })
+    }
+
+    /// Stakes of `address` in the root chain staking contract, including unlocked stakes
+    pub async fn get_root_locked_stakes(&self, address: &QkcAddress) -> Result<LockedStakes> {
+        let owner = address.coinbase;
+        let tokens = self.call_function(&contract_address(), &staking_contract()?, "stakes", &[Token::Address(owner)], Block::Latest).await?;
+
+        // the node returns no data if the contract is not deployed yet
+        if tokens.is_empty() {
+            return Ok(LockedStakes {
+                owner,
+                unlocked: false,
+                withdrawable_timestamp: 0,
+                amount: U256::zero(),
+                signer: H160::zero()
+            });
+        }
+
+        LockedStakes::from_tokens(owner, tokens)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn staking_call_test() {
+        assert_eq!(hex::encode(StakingCall::AddStakes(U256::exp10(18)).encode().unwrap()), "f83d08ba");
+        assert_eq!(StakingCall::AddStakes(U256::exp10(18)).value(), U256::exp10(18));
+        assert_eq!(hex::encode(StakingCall::RequestWithdraw.encode().unwrap()), "a69df4b5");
+        assert_eq!(hex::encode(StakingCall::WithdrawAll.encode().unwrap()), "853828b6");
+        assert_eq!(StakingCall::WithdrawAll.value(), U256::zero());
+
+        let withdraw = StakingCall::Withdraw(U256::from(16)).encode().unwrap();
+        assert_eq!(hex::encode(&withdraw[..4]), "2e1a7d4d");
+        assert_eq!(withdraw.len(), 36);
+        assert_eq!(U256::from_big_endian(&withdraw[4..]), U256::from(16));
+
+        let signer = StakingCall::SetSigner(H160::repeat_byte(0x35)).encode().unwrap();
+        assert_eq!(hex::encode(&signer[..4]), "6c19e783");
+        assert_eq!(signer[16..], [0x35; 20]);
+    }
+
+    #[test]
+    fn locked_stakes_test() {
+        let function = staking_contract().unwrap().function("stakes").unwrap().clone();
+        let owner = H160::repeat_byte(0x11);
+        let output = ethabi::encode(&[
+            Token::Bool(true),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::Uint(U256::exp10(18)),
+            Token::Address(H160::zero())
+        ]);
+
+        let stakes = LockedStakes::from_tokens(owner, function.decode_output(&output).unwrap()).unwrap();
+        assert!(stakes.unlocked);
+        assert_eq!(stakes.withdrawable_timestamp, 1_700_000_000);
+        assert_eq!(stakes.amount, U256::exp10(18));
+        assert_eq!(stakes.locked_amount(), U256::zero());
+        assert_eq!(stakes.signer(), owner);
+        assert!(stakes.in_unlock_period(1_600_000_000));
+        assert!(!stakes.in_unlock_period(1_700_000_000));
+
+        let signer = H160::repeat_byte(0x22);
+        let output = ethabi::encode(&[Token::Bool(false), Token::Uint(U256::zero()), Token::Uint(U256::exp10(18)), Token::Address(signer)]);
+        let stakes = LockedStakes::from_tokens(owner, function.decode_output(&output).unwrap()).unwrap();
+        assert_eq!(stakes.locked_amount(), U256::exp10(18));
+        assert_eq!(stakes.signer(), signer);
+    }
+
+    #[tokio::test]
+    async fn root_staking_transaction_test() {
+        use std::sync::Arc;
+
+        use crate::{QkcWeb3, RetryPolicy, qkc::{Method, NetworkInfo}, transport::MockTransport, wallet::Wallet};
+
+        let wallet = Wallet::from_hex("4646464646464646464646464646464646464646464646464646464646464646").unwrap();
+        let network_info = |network_id: u64| NetworkInfo {
+            network_id: network_id.into(),
+            chain_size: 1.into(),
+            shard_sizes: vec![1.into()],
+            syncing: false,
+            mining: false,
+            shard_server_count: 1
+        };
+
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(Method::GetTransactionCount, "0x7")
+            .respond(Method::NetworkInfo, network_info(255))
+            .respond(Method::NetworkInfo, network_info(1 << 32));
+        let web3 = QkcWeb3::with_transport(mock.clone(), RetryPolicy::no_retries());
+
+        // the owner's shard does not matter, the nonce is read in the contract's shard
+        let owner = wallet.address(0x0003_0001);
+        let tx = web3.qkc().root_staking_transaction(&owner, StakingCall::AddStakes(U256::exp10(18)), U256::from(1_000_000_000)).await.unwrap();
+        assert_eq!(mock.requests()[0]["params"], serde_json::json!([wallet.address(0x0000_0001).to_string()]));
+
+        let raw = wallet.sign_transaction(tx).unwrap().raw();
+        let rlp = rlp::Rlp::new(&raw);
+        assert_eq!(rlp.item_count().unwrap(), 15);
+        assert_eq!(rlp.val_at::<u64>(0).unwrap(), 7); // nonce
+        assert_eq!(rlp.val_at::<u64>(1).unwrap(), 1_000_000_000); // gas price
+        assert_eq!(rlp.val_at::<u64>(2).unwrap(), STAKING_GAS);
+        assert_eq!(rlp.val_at::<Vec<u8>>(3).unwrap(), contract_address().coinbase.as_bytes());
+        assert_eq!(rlp.val_at::<u64>(4).unwrap(), 10u64.pow(18)); // value
+        assert_eq!(rlp.val_at::<Vec<u8>>(5).unwrap(), StakingCall::AddStakes(U256::zero()).encode().unwrap());
+        assert_eq!(rlp.val_at::<u64>(6).unwrap(), 255); // network id
+        assert_eq!(rlp.val_at::<Vec<u8>>(7).unwrap(), [0, 0, 0, 1]); // from full shard key
+        assert_eq!(rlp.val_at::<Vec<u8>>(8).unwrap(), [0, 0, 0, 1]); // to full shard key
+
+        let res = web3.qkc().root_staking_transaction(&owner, StakingCall::WithdrawAll, U256::zero()).await;
+        assert!(matches!(res, Err(Error::InvalidResponse(_))));
+    }
+}
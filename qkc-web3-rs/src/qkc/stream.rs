@@ -0,0 +1,365 @@
+use std::{collections::VecDeque, time::Duration};
+
+use futures::{Stream, stream};
+
+use crate::{error::Result, types::H256};
+
+use super::{Block, MinorBlockData, MinorBlockHeader, Qkc, RootBlockData};
+
+/// Number of recent blocks kept to find the common ancestor of a reorg,
+/// deeper reorgs restart the stream at the latest block
+pub const MAX_REORG_DEPTH: usize = 64;
+
+/// Block that can be followed with a block stream
+pub trait ChainBlock {
+    fn height(&self) -> u64;
+    fn hash(&self) -> H256;
+    fn parent_hash(&self) -> H256;
+}
+
+impl ChainBlock for RootBlockData {
+    fn height(&self) -> u64 {
+        *self.height
+    }
+
+    fn hash(&self) -> H256 {
+        self.hash
+    }
+
+    fn parent_hash(&self) -> H256 {
+        self.hash_prev_block
+    }
+}
+
+impl ChainBlock for MinorBlockData {
+    fn height(&self) -> u64 {
+        *self.height
+    }
+
+    fn hash(&self) -> H256 {
+        self.hash
+    }
+
+    fn parent_hash(&self) -> H256 {
+        self.hash_prev_minor_block
+    }
+}
+
+impl ChainBlock for MinorBlockHeader {
+    fn height(&self) -> u64 {
+        *self.height
+    }
+
+    fn hash(&self) -> H256 {
+        self.hash
+    }
+
+    fn parent_hash(&self) -> H256 {
+        self.hash_prev_minor_block
+    }
+}
+
+/// Item of a block stream
+#[derive(Debug, Clone)]
+pub enum BlockEvent<B> {
+    /// Next block of the chain, its parent is the previously yielded block
+    Block(B),
+    /// The blocks above `height` were replaced, the new blocks from `height + 1` follow.
+    /// `depth` is the number of yielded blocks that are no longer part of the chain
+    Reorg {
+        height: u64,
+        depth: u64
+    }
+}
+
+pub(super) trait BlockSource<B> {
+    async fn latest(&self) -> Result<B>;
+
+    async fn by_heights(&self, heights: &[u64]) -> Result<Vec<B>>;
+}
+
+struct RootSource(Qkc);
+
+impl BlockSource<RootBlockData> for RootSource {
+    async fn latest(&self) -> Result<RootBlockData> {
+        self.0.get_root_block_by_height(Block::Latest, false).await
+    }
+
+    async fn by_heights(&self, heights: &[u64]) -> Result<Vec<RootBlockData>> {
+        self.0.get_root_blocks_by_height(heights, false).await
+    }
+}
+
+pub(super) struct MinorSource {
+    pub(super) qkc: Qkc,
+    pub(super) full_shard_key: String,
+    pub(super) include_transactions: bool
+}
+
+impl BlockSource<MinorBlockData> for MinorSource {
+    async fn latest(&self) -> Result<MinorBlockData> {
+        self.qkc.get_minor_block_by_height(self.full_shard_key.clone(), Block::Latest, self.include_transactions, false).await
+    }
+
+    async fn by_heights(&self, heights: &[u64]) -> Result<Vec<MinorBlockData>> {
+        self.qkc.get_minor_blocks_by_height(&self.full_shard_key, heights, self.include_transactions, false).await
+    }
+}
+
+/// Queues every new block of a chain once, refetching skipped heights and replaced blocks
+#[derive(Debug, Clone)]
+pub(super) struct BlockFollower<B> {
+    /// Height and hash of the most recently yielded blocks
+    recent: VecDeque<(u64, H256)>,
+    pending: VecDeque<BlockEvent<B>>
+}
+
+impl<B: ChainBlock> BlockFollower<B> {
+    pub(super) fn new() -> Self {
+        Self {
+            recent: VecDeque::with_capacity(MAX_REORG_DEPTH),
+            pending: VecDeque::new()
+        }
+    }
+
+    /// Oldest queued event
+    pub(super) fn pop(&mut self) -> Option<BlockEvent<B>> {
+        self.pending.pop_front()
+    }
+
+    /// Queues the blocks added to `source` since the last poll, the first poll queues the latest block
+    pub(super) async fn poll<S: BlockSource<B>>(&mut self, source: &S) -> Result<()> {
+        let latest = source.latest().await?;
+
+        let Some(&(last_height, last_hash)) = self.recent.back() else {
+            self.push(latest);
+            return Ok(());
+        };
+
+        if latest.hash() == last_hash || self.recent.contains(&(latest.height(), latest.hash())) {
+            return Ok(());
+        }
+
+        if latest.height() > last_height {
+            let mut blocks = source.by_heights(&(last_height + 1..latest.height()).collect::<Vec<_>>()).await?;
+
+            if blocks.first().unwrap_or(&latest).parent_hash() == last_hash {
+                blocks.push(latest);
+                self.extend(blocks);
+
+                return Ok(());
+            }
+        }
+
+        self.reorg(source, latest).await
+    }
+
+    /// Finds the highest yielded block that is still part of the chain and queues the blocks above it
+    async fn reorg<S: BlockSource<B>>(&mut self, source: &S, latest: B) -> Result<()> {
+        let heights: Vec<u64> = self.recent.iter().map(|(height, _)| *height).filter(|height| *height <= latest.height()).collect();
+        let canonical = source.by_heights(&heights).await?;
+
+        let ancestor = self.recent.iter()
+            .zip(canonical.iter())
+            .filter(|((height, hash), block)| *height == block.height() && *hash == block.hash())
+            .map(|((height, _), _)| *height)
+            .last();
+
+        let last_height = self.recent.back().map(|(height, _)| *height).unwrap_or_default();
+
+        let Some(ancestor) = ancestor else {
+            self.pending.push_back(BlockEvent::Reorg {
+                height: latest.height().min(last_height + 1).saturating_sub(1),
+                depth: self.recent.len() as u64
+            });
+            self.recent.clear();
+            self.push(latest);
+
+            return Ok(());
+        };
+
+        self.recent.retain(|(height, _)| *height <= ancestor);
+        self.pending.push_back(BlockEvent::Reorg {
+            height: ancestor,
+            depth: last_height - ancestor
+        });
+
+        let mut blocks = source.by_heights(&(ancestor + 1..latest.height()).collect::<Vec<_>>()).await?;
+        blocks.push(latest);
+        self.extend(blocks);
+
+        Ok(())
+    }
+
+    /// Queues `blocks` up to the first one that does not extend the previous block,
+    /// the rest is refetched by the next poll
+    fn extend(&mut self, blocks: Vec<B>) {
+        for block in blocks {
+            if self.recent.back().is_some_and(|(_, hash)| *hash != block.parent_hash()) {
+                break;
+            }
+
+            self.push(block);
+        }
+    }
+
+    fn push(&mut self, block: B) {
+        if self.recent.len() == MAX_REORG_DEPTH {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back((block.height(), block.hash()));
+        self.pending.push_back(BlockEvent::Block(block));
+    }
+}
+
+/// Polls a chain every `interval` while no blocks are queued
+struct BlockPoller<S, B> {
+    source: S,
+    interval: Duration,
+    follower: BlockFollower<B>,
+    idle: bool
+}
+
+impl<S: BlockSource<B>, B: ChainBlock> BlockPoller<S, B> {
+    fn new(source: S, interval: Duration) -> Self {
+        Self {
+            source,
+            interval,
+            follower: BlockFollower::new(),
+            idle: false
+        }
+    }
+
+    async fn next(&mut self) -> Result<BlockEvent<B>> {
+        loop {
+            if let Some(event) = self.follower.pop() {
+                return Ok(event);
+            }
+
+            if self.idle {
+                tokio::time::sleep(self.interval).await;
+            }
+
+            // errors are yielded but the stream continues after the next interval
+            self.idle = true;
+            self.follower.poll(&self.source).await?;
+            self.idle = self.follower.pending.is_empty();
+        }
+    }
+
+    fn into_stream(self) -> impl Stream<Item = Result<BlockEvent<B>>> {
+        stream::unfold(self, |mut poller| async move {
+            let event = poller.next().await;
+            Some((event, poller))
+        })
+    }
+}
+
+impl Qkc {
+    /// Endless stream of new root blocks starting at the latest block, polled every `interval`.
+    /// Skipped heights are fetched so that every block is yielded once in height order,
+    /// replaced blocks are announced with [`BlockEvent::Reorg`]
+    pub fn root_block_stream(&self, interval: Duration) -> impl Stream<Item = Result<BlockEvent<RootBlockData>>> {
+        BlockPoller::new(RootSource(self.clone()), interval).into_stream()
+    }
+
+    /// Same as [`Qkc::root_block_stream`] for the minor blocks of the shard of `full_shard_key`
+    pub fn minor_block_stream(&self, full_shard_key: &str, include_transactions: bool, interval: Duration) -> impl Stream<Item = Result<BlockEvent<MinorBlockData>>> {
+        let source = MinorSource {
+            qkc: self.clone(),
+            full_shard_key: full_shard_key.to_string(),
+            include_transactions
+        };
+
+        BlockPoller::new(source, interval).into_stream()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestBlock(u64, H256, H256);
+
+    impl ChainBlock for TestBlock {
+        fn height(&self) -> u64 {
+            self.0
+        }
+
+        fn hash(&self) -> H256 {
+            self.1
+        }
+
+        fn parent_hash(&self) -> H256 {
+            self.2
+        }
+    }
+
+    /// Chain whose block at height `h` has hash `fork * 1000 + h`
+    #[derive(Default)]
+    struct TestChain(Mutex<Vec<TestBlock>>);
+
+    impl TestChain {
+        fn extend(&self, fork: u64, from: u64, to: u64) {
+            let mut blocks = self.0.lock().unwrap();
+            blocks.truncate(from as usize);
+
+            for height in from..=to {
+                let parent = blocks.last().map(|b| b.1).unwrap_or_default();
+                blocks.push(TestBlock(height, H256::from_low_u64_be(fork * 1000 + height), parent));
+            }
+        }
+    }
+
+    impl BlockSource<TestBlock> for &TestChain {
+        async fn latest(&self) -> Result<TestBlock> {
+            Ok(self.0.lock().unwrap().last().unwrap().clone())
+        }
+
+        async fn by_heights(&self, heights: &[u64]) -> Result<Vec<TestBlock>> {
+            let blocks = self.0.lock().unwrap();
+            Ok(heights.iter().map(|h| blocks[*h as usize].clone()).collect())
+        }
+    }
+
+    fn block_height(event: BlockEvent<TestBlock>) -> u64 {
+        match event {
+            BlockEvent::Block(block) => block.0,
+            other => panic!("unexpected {other:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn block_stream_test() {
+        let chain = TestChain::default();
+        chain.extend(0, 0, 10);
+
+        let mut follower = BlockPoller::new(&chain, Duration::ZERO);
+        assert_eq!(block_height(follower.next().await.unwrap()), 10);
+
+        // skipped heights are fetched
+        chain.extend(0, 11, 13);
+        for height in 11..=13 {
+            assert_eq!(block_height(follower.next().await.unwrap()), height);
+        }
+
+        // blocks above 11 are replaced by a longer fork
+        chain.extend(1, 12, 14);
+        assert!(matches!(follower.next().await.unwrap(), BlockEvent::Reorg { height: 11, depth: 2 }));
+        for height in 12..=14 {
+            let BlockEvent::Block(block) = follower.next().await.unwrap() else { panic!() };
+            assert_eq!(block, chain.0.lock().unwrap()[height as usize]);
+        }
+
+        let stream = follower.into_stream();
+        futures::pin_mut!(stream);
+        chain.extend(1, 15, 15);
+        assert_eq!(block_height(stream.next().await.unwrap().unwrap()), 15);
+    }
+}
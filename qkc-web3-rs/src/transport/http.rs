@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{endpoints::Endpoints, error::{Error, Result}, qkc::{Method, MinorBlockData, Request, RootBlockData, RpcResponse}};
+
+use super::Transport;
+
+/// JSON-RPC over HTTP POST, failing over between the configured endpoints
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    endpoints: Endpoints,
+    timeout: Duration,
+    full_shard_key: Option<u32>
+}
+
+impl HttpTransport {
+    /// Requests go to the healthy endpoints first, ordered by the highest root block height (then the minor block
+    /// height of the shard set with [`HttpTransport::with_shard`]) and then by their position in `urls`, failing over
+    /// to the next one if an endpoint cannot be reached. `timeout` applies to every request
+    pub fn new(urls: Vec<String>, timeout: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoints: Endpoints::new(urls),
+            timeout,
+            full_shard_key: None
+        }
+    }
+
+    /// Ranks endpoints with the same root block height by the minor block height of the shard of `full_shard_key`,
+    /// e.g. the shard the caller mines on
+    pub fn with_shard(mut self, full_shard_key: u32) -> Self {
+        self.full_shard_key = Some(full_shard_key);
+        self
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Queries the root block height (and the minor block height of the shard set with [`HttpTransport::with_shard`])
+    /// of every endpoint to prefer the ones that are in sync
+    pub async fn check_endpoints(&self) {
+        let checks = self.endpoints.urls().map(|url| async {
+            match self.heights(url).await {
+                Ok((root_height, minor_height)) => {
+                    self.endpoints.report_height(url, root_height, minor_height);
+                    self.endpoints.report_success(url);
+                },
+                Err(_) => self.endpoints.report_failure(url)
+            }
+        });
+
+        futures::future::join_all(checks).await;
+    }
+
+    async fn heights(&self, url: &str) -> Result<(u64, Option<u64>)> {
+        let req = Request::<Value>::new(Method::GetRootBlockByHeight, Some(vec![Value::Null, false.into()]));
+        let root = self.post_to::<_, RpcResponse<RootBlockData>>(url, &req).await?.into_result()?;
+
+        let minor = match self.full_shard_key {
+            Some(full_shard_key) => {
+                let req = Request::<Value>::new(Method::GetMinorBlockByHeight, Some(vec![format!("{full_shard_key:#010x}").into(), Value::Null, false.into(), false.into()]));
+                Some(*self.post_to::<_, RpcResponse<MinorBlockData>>(url, &req).await?.into_result()?.height)
+            },
+            None => None
+        };
+
+        Ok((*root.height, minor))
+    }
+
+    /// Posts `req` to the preferred endpoint, failing over to the next one if the endpoint failed (see [`Error::is_transient`]).
+    /// Endpoint health is checked in the background so the request does not wait for slow endpoints
+    async fn post<R: Serialize + ?Sized>(&self, req: &R) -> Result<Value> {
+        if self.endpoints.check_due() {
+            let transport = self.clone();
+            tokio::spawn(async move { transport.check_endpoints().await });
+        }
+
+        let mut last_error = None;
+
+        for url in self.endpoints.ordered() {
+            match self.post_to(url, req).await {
+                Ok(res) => {
+                    self.endpoints.report_success(url);
+                    return Ok(res);
+                },
+                // other errors are caused by the request and leave the endpoint's health as is
+                Err(e) if e.is_transient() => {
+                    self.endpoints.report_failure(url);
+                    last_error = Some(e);
+                },
+                Err(e) => return Err(e)
+            }
+        }
+
+        Err(last_error.unwrap_or(Error::NoEndpoint))
+    }
+
+    async fn post_to<R: Serialize + ?Sized, T: serde::de::DeserializeOwned>(&self, url: &str, req: &R) -> Result<T> {
+        let res = self.client.post(url).timeout(self.timeout).json(req).send().await?;
+        let status = res.status();
+        let body = res.bytes().await?;
+
+        match serde_json::from_slice::<T>(&body) {
+            Ok(res) => Ok(res),
+            Err(_) if !status.is_success() => Err(Error::HttpStatus {
+                status: status.as_u16(),
+                body: String::from_utf8_lossy(&body).into_owned()
+            }),
+            Err(e) => Err(e.into())
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, req: Value) -> BoxFuture<'_, Result<Value>> {
+        Box::pin(async move { self.post(&req).await })
+    }
+
+    fn send_batch(&self, reqs: Vec<Value>) -> BoxFuture<'_, Result<Value>> {
+        Box::pin(async move { self.post(&reqs).await })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::{Arc, atomic::{AtomicUsize, Ordering}}, time::Duration};
+
+    use serde_json::json;
+    use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+    use crate::Error;
+
+    use super::*;
+
+    /// Local http node answering every request with `status` and `body`, returns its url and the number of requests
+    async fn serve(status: &'static str, body: String) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let count = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut req = Vec::new();
+                let mut buf = [0; 4096];
+
+                // reads the headers and the body of the request
+                while let Ok(read @ 1..) = stream.read(&mut buf).await {
+                    req.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&req).to_lowercase();
+
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text.lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .and_then(|length| length.trim().parse::<usize>().ok())
+                            .unwrap_or_default();
+
+                        if req.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+
+                count.fetch_add(1, Ordering::Relaxed);
+                let res = format!("HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}", body.len());
+                stream.write_all(res.as_bytes()).await.ok();
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn failover_test() {
+        let req = json!({"jsonrpc": "2.0", "method": "networkInfo", "params": [], "id": 1});
+        let response = json!({"jsonrpc": "2.0", "result": "0x1", "id": 1}).to_string();
+        let (healthy, healthy_requests) = serve("200 OK", response).await;
+
+        // a rejected request fails the same way on every endpoint
+        let (bad_request, _) = serve("400 Bad Request", "bad request".into()).await;
+        let transport = HttpTransport::new(vec![bad_request.clone(), healthy.clone()], Duration::from_secs(5));
+        // skips the background health check so that only the request reaches the endpoints
+        assert!(transport.endpoints().check_due());
+        assert!(matches!(transport.send(req.clone()).await, Err(Error::HttpStatus { status: 400, .. })));
+        assert_eq!(healthy_requests.load(Ordering::Relaxed), 0);
+        let health = transport.endpoints().health(&bad_request).unwrap();
+        assert!(health.is_healthy());
+        assert_eq!(health.failures, 0);
+
+        let (unavailable, _) = serve("503 Service Unavailable", "unavailable".into()).await;
+        let transport = HttpTransport::new(vec![unavailable.clone(), healthy.clone()], Duration::from_secs(5));
+        assert!(transport.endpoints().check_due());
+        assert_eq!(transport.send(req).await.unwrap()["result"], "0x1");
+        assert_eq!(healthy_requests.load(Ordering::Relaxed), 1);
+        assert!(!transport.endpoints().health(&unavailable).unwrap().is_healthy());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{collections::{HashMap, VecDeque}, sync::Mutex};
+
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::{error::{Result, RpcError}, qkc::Method};
+
+use super::Transport;
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: HashMap<String, VecDeque<std::result::Result<Value, RpcError>>>,
+    requests: Vec<Value>
+}
+
+/// In-memory transport that answers with queued responses per method and records every request.
+///
+/// Responses of a method are returned in the order they were queued, the last one is repeated.
+/// Methods without responses are answered with a "method not found" error.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    state: Mutex<MockState>
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `result` as the result of the next `method` request
+    pub fn respond<T: Serialize>(&self, method: Method, result: T) -> &Self {
+        self.queue(method, Ok(serde_json::to_value(result).expect("serializable result")))
+    }
+
+    /// Queues `error` as the error of the next `method` request
+    pub fn respond_error(&self, method: Method, error: RpcError) -> &Self {
+        self.queue(method, Err(error))
+    }
+
+    fn queue(&self, method: Method, response: std::result::Result<Value, RpcError>) -> &Self {
+        let method = method_name(method);
+        self.state.lock().unwrap().responses.entry(method).or_default().push_back(response);
+        self
+    }
+
+    /// Requests received so far, batch requests are recorded individually
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Number of requests received for `method`
+    pub fn count(&self, method: Method) -> usize {
+        let method = method_name(method);
+        self.state.lock().unwrap().requests.iter().filter(|req| req["method"] == method.as_str()).count()
+    }
+
+    fn answer(&self, req: Value) -> Value {
+        let mut state = self.state.lock().unwrap();
+        let method = req["method"].as_str().unwrap_or_default().to_string();
+
+        let response = match state.responses.get_mut(&method) {
+            Some(responses) if responses.len() > 1 => responses.pop_front(),
+            Some(responses) => responses.front().cloned(),
+            None => None
+        };
+
+        let res = match response {
+            Some(Ok(result)) => json!({"jsonrpc": "2.0", "result": result, "id": req["id"]}),
+            Some(Err(error)) => json!({"jsonrpc": "2.0", "error": error, "id": req["id"]}),
+            None => json!({"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": req["id"]})
+        };
+
+        state.requests.push(req);
+
+        res
+    }
+}
+
+fn method_name(method: Method) -> String {
+    serde_json::to_value(method).ok().and_then(|m| m.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+impl Transport for MockTransport {
+    fn send(&self, req: Value) -> BoxFuture<'_, Result<Value>> {
+        Box::pin(async move { Ok(self.answer(req)) })
+    }
+
+    fn send_batch(&self, reqs: Vec<Value>) -> BoxFuture<'_, Result<Value>> {
+        Box::pin(async move { Ok(Value::Array(reqs.into_iter().map(|req| self.answer(req)).collect())) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::{Error, QkcWeb3, RetryPolicy, RpcError, qkc::Method, types::QkcAddress};
+
+    use super::MockTransport;
+
+    #[tokio::test]
+    async fn mock_transport_test() {
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(Method::GetTransactionCount, "0x1")
+            .respond(Method::GetTransactionCount, "0x2")
+            .respond_error(Method::NetworkInfo, RpcError { code: -32000, message: "syncing".into(), data: None });
+
+        let web3 = QkcWeb3::with_transport(mock.clone(), RetryPolicy::no_retries());
+        let address = QkcAddress::new_full("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000").unwrap();
+
+        assert_eq!(web3.qkc().get_transaction_count(&address).await.unwrap(), 1);
+        assert_eq!(web3.qkc().get_transaction_count(&address).await.unwrap(), 2);
+        assert_eq!(web3.qkc().get_transaction_count(&address).await.unwrap(), 2);
+        assert!(matches!(web3.qkc().network_info().await, Err(Error::Rpc(e)) if e.code == -32000));
+        assert!(matches!(web3.qkc().get_balances(&address).await, Err(Error::Rpc(e)) if e.code == -32601));
+
+        assert_eq!(mock.count(Method::GetTransactionCount), 3);
+        assert_eq!(mock.requests()[0]["params"], json!(["0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00000000"]));
+    }
+}
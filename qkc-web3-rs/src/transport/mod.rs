@@ -0,0 +1,31 @@
+use std::fmt;
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::error::Result;
+
+mod http;
+mod mock;
+mod ws;
+
+pub use http::HttpTransport;
+pub use mock::MockTransport;
+pub use ws::{WsClient, Subscription};
+
+/// Delivers JSON-RPC messages to a node, used by [`crate::qkc::Qkc`] for every request.
+///
+/// Requests and responses are complete JSON-RPC objects, retries are handled by the caller.
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Sends a single request and returns its response object
+    fn send(&self, req: Value) -> BoxFuture<'_, Result<Value>>;
+
+    /// Sends a batch of requests and returns the array of responses, or a single
+    /// error response if the node rejected the batch as a whole
+    fn send_batch(&self, reqs: Vec<Value>) -> BoxFuture<'_, Result<Value>>;
+
+    /// Connection that subscriptions are created on, `None` if the transport does not support them
+    fn ws(&self) -> Option<&WsClient> {
+        None
+    }
+}
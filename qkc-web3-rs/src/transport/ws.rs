@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    task::{Context, Poll},
+    time::Duration
+};
+
+use futures::{SinkExt, Stream, StreamExt, future::BoxFuture, stream::{SplitSink, SplitStream}};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::{net::TcpStream, sync::{mpsc, oneshot}};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message};
+
+use crate::{error::{Error, Result}, qkc::{Method, Request, RpcResponse}};
+
+use super::Transport;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+struct Pending {
+    response: oneshot::Sender<Value>,
+    /// Receives the notifications if the request creates a subscription
+    subscription: Option<mpsc::UnboundedSender<Value>>
+}
+
+#[derive(Default)]
+struct State {
+    pending: HashMap<usize, Pending>,
+    subscriptions: HashMap<String, mpsc::UnboundedSender<Value>>,
+    closed: bool
+}
+
+impl State {
+    fn dispatch(&mut self, message: Value) {
+        if message["method"] == "subscription" {
+            let params = &message["params"];
+            let Some(id) = params["subscription"].as_str() else { return };
+
+            if let Some(subscription) = self.subscriptions.get(id) {
+                if subscription.send(params["result"].clone()).is_err() {
+                    self.subscriptions.remove(id);
+                }
+            }
+
+            return;
+        }
+
+        let Some(pending) = message["id"].as_u64().and_then(|id| self.pending.remove(&(id as usize))) else { return };
+
+        // registered before the response is handed out so that no notification is missed
+        if let (Some(subscription), Some(id)) = (pending.subscription, message["result"].as_str()) {
+            self.subscriptions.insert(id.to_string(), subscription);
+        }
+
+        pending.response.send(message).ok();
+    }
+}
+
+struct Connection {
+    sink: tokio::sync::Mutex<SplitSink<Socket, Message>>,
+    state: Arc<Mutex<State>>
+}
+
+impl Connection {
+    async fn open(url: &str) -> Result<Self> {
+        let (socket, _) = connect_async(url).await.map_err(Box::new)?;
+        let (sink, stream) = socket.split();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        tokio::spawn(Self::read(stream, state.clone()));
+
+        Ok(Self {
+            sink: tokio::sync::Mutex::new(sink),
+            state
+        })
+    }
+
+    async fn read(mut stream: SplitStream<Socket>, state: Arc<Mutex<State>>) {
+        while let Some(Ok(message)) = stream.next().await {
+            let message = match message {
+                Message::Text(text) => serde_json::from_str(&text),
+                Message::Binary(data) => serde_json::from_slice(&data),
+                Message::Close(_) => break,
+                _ => continue
+            };
+
+            if let Ok(message) = message {
+                state.lock().unwrap().dispatch(message);
+            }
+        }
+
+        // dropping the senders fails pending requests and ends the subscriptions
+        let mut state = state.lock().unwrap();
+        state.closed = true;
+        state.pending.clear();
+        state.subscriptions.clear();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+}
+
+/// JSON-RPC client over a websocket connection that supports subscriptions.
+/// The connection is reopened by the next request once it was closed, subscriptions end with it
+#[derive(Clone)]
+pub struct WsClient {
+    url: String,
+    timeout: Duration,
+    connection: Arc<tokio::sync::Mutex<Option<Arc<Connection>>>>,
+    next_id: Arc<AtomicUsize>
+}
+
+impl fmt::Debug for WsClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsClient").field("url", &self.url).field("timeout", &self.timeout).finish()
+    }
+}
+
+impl WsClient {
+    /// Connects to `url`, requests that are not answered within `timeout` fail with [`Error::Timeout`]
+    pub async fn connect(url: &str, timeout: Duration) -> Result<Self> {
+        let connection = Connection::open(url).await?;
+
+        Ok(Self {
+            url: url.to_string(),
+            timeout,
+            connection: Arc::new(tokio::sync::Mutex::new(Some(Arc::new(connection)))),
+            next_id: Arc::new(AtomicUsize::new(1))
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn connection(&self) -> Result<Arc<Connection>> {
+        let mut connection = self.connection.lock().await;
+
+        match connection.as_ref() {
+            Some(open) if !open.is_closed() => Ok(open.clone()),
+            _ => {
+                let open = Arc::new(Connection::open(&self.url).await?);
+                *connection = Some(open.clone());
+                Ok(open)
+            }
+        }
+    }
+
+    async fn request_value(&self, req: Value, subscription: Option<mpsc::UnboundedSender<Value>>) -> Result<Value> {
+        let connection = self.connection().await?;
+        self.request_on(&connection, req, subscription).await
+    }
+
+    async fn request_on(&self, connection: &Connection, mut req: Value, subscription: Option<mpsc::UnboundedSender<Value>>) -> Result<Value> {
+        // ids are unique per connection, the caller's id is restored on the response
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let caller_id = std::mem::replace(&mut req["id"], id.into());
+        let (tx, rx) = oneshot::channel();
+
+        {
+            let mut state = connection.state.lock().unwrap();
+
+            if state.closed {
+                return Err(Error::ConnectionClosed);
+            }
+
+            state.pending.insert(id, Pending { response: tx, subscription });
+        }
+
+        let sent = connection.sink.lock().await.send(Message::text(req.to_string())).await;
+
+        if let Err(e) = sent {
+            connection.state.lock().unwrap().pending.remove(&id);
+            return Err(Box::new(e).into());
+        }
+
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(mut res)) => {
+                res["id"] = caller_id;
+                Ok(res)
+            },
+            Ok(Err(_)) => Err(Error::ConnectionClosed),
+            Err(_) => {
+                connection.state.lock().unwrap().pending.remove(&id);
+                Err(Error::Timeout)
+            }
+        }
+    }
+
+    /// Creates a subscription with the `subscribe` RPC, `params` start with the subscription type
+    pub async fn subscribe<T: DeserializeOwned>(&self, params: Vec<Value>) -> Result<Subscription<T>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let req = serde_json::to_value(Request::new(Method::Subscribe, Some(params)))?;
+
+        let connection = self.connection().await?;
+        let res: RpcResponse<String> = serde_json::from_value(self.request_on(&connection, req, Some(tx)).await?)?;
+
+        Ok(Subscription {
+            id: res.into_result()?,
+            notifications: rx,
+            client: self.clone(),
+            connection,
+            unsubscribed: false,
+            _result: PhantomData
+        })
+    }
+
+    /// Cancels the subscription `id`, returns `false` if the node did not know it
+    pub async fn unsubscribe(&self, id: &str) -> Result<bool> {
+        let connection = self.connection().await?;
+        self.unsubscribe_on(&connection, id).await
+    }
+
+    async fn unsubscribe_on(&self, connection: &Connection, id: &str) -> Result<bool> {
+        connection.state.lock().unwrap().subscriptions.remove(id);
+
+        let req = serde_json::to_value(Request::new(Method::Unsubscribe, Some(vec![id])))?;
+        let res: RpcResponse<bool> = serde_json::from_value(self.request_on(connection, req, None).await?)?;
+
+        res.into_result()
+    }
+}
+
+impl Transport for WsClient {
+    fn send(&self, req: Value) -> BoxFuture<'_, Result<Value>> {
+        Box::pin(self.request_value(req, None))
+    }
+
+    /// Batches are sent as individual requests
+    fn send_batch(&self, reqs: Vec<Value>) -> BoxFuture<'_, Result<Value>> {
+        Box::pin(async move {
+            let res = futures::future::try_join_all(reqs.into_iter().map(|req| self.request_value(req, None))).await?;
+
+            Ok(Value::Array(res))
+        })
+    }
+
+    fn ws(&self) -> Option<&WsClient> {
+        Some(self)
+    }
+}
+
+/// Notifications of a subscription, ends when the connection is closed.
+/// Dropping the subscription cancels it in the background
+pub struct Subscription<T> {
+    id: String,
+    notifications: mpsc::UnboundedReceiver<Value>,
+    client: WsClient,
+    /// Connection the subscription was created on, the id is only known there
+    connection: Arc<Connection>,
+    unsubscribed: bool,
+    _result: PhantomData<fn() -> T>
+}
+
+impl<T> fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription").field("id", &self.id).finish()
+    }
+}
+
+impl<T> Subscription<T> {
+    /// Subscription id assigned by the node
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Cancels the subscription, returns `false` if the node did not know it
+    pub async fn unsubscribe(mut self) -> Result<bool> {
+        self.unsubscribed = true;
+        self.client.unsubscribe_on(&self.connection, &self.id).await
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        // the node keeps sending notifications until the subscription is cancelled
+        if self.unsubscribed || self.connection.is_closed() {
+            return;
+        }
+
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let (client, connection, id) = (self.client.clone(), self.connection.clone(), std::mem::take(&mut self.id));
+            runtime.spawn(async move { client.unsubscribe_on(&connection, &id).await });
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Stream for Subscription<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notifications.poll_recv(cx).map(|notification| notification.map(|n| Ok(serde_json::from_value(n)?)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use tokio::{net::TcpListener, sync::mpsc};
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    use crate::{QkcWeb3, RetryPolicy, qkc::{BatchCall, Method, NetworkInfo}};
+
+    /// Local websocket node answering `networkInfo`, `subscribe` and `unsubscribe`,
+    /// every subscription immediately gets two notifications. Returns the url and the methods received
+    async fn serve() -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (methods, received) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                let req: Value = serde_json::from_str(&text).unwrap();
+                methods.send(req["method"].as_str().unwrap().to_string()).ok();
+                let result = match req["method"].as_str().unwrap() {
+                    "networkInfo" => json!({
+                        "networkId": "0x1", "chainSize": "0x8", "shardSizes": ["0x1"],
+                        "syncing": false, "mining": false, "shardServerCount": 1
+                    }),
+                    "subscribe" => json!("0xabc"),
+                    "unsubscribe" => json!(true),
+                    _ => Value::Null
+                };
+
+                let res = json!({"jsonrpc": "2.0", "result": result, "id": req["id"]});
+                socket.send(Message::text(res.to_string())).await.unwrap();
+
+                if req["method"] == "subscribe" {
+                    for height in 1..=2 {
+                        let notification = json!({
+                            "jsonrpc": "2.0", "method": "subscription",
+                            "params": {"subscription": "0xabc", "result": {"height": height}}
+                        });
+                        socket.send(Message::text(notification.to_string())).await.unwrap();
+                    }
+                }
+            }
+        });
+
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn ws_test() {
+        let (url, _) = serve().await;
+        let web3 = QkcWeb3::connect_ws(&url, RetryPolicy::no_retries()).await.unwrap();
+
+        let info = web3.qkc().network_info().await.unwrap();
+        assert_eq!(*info.chain_size, 8);
+
+        let calls = vec![BatchCall::new(Method::NetworkInfo, None); 3];
+        let infos: Vec<_> = web3.qkc().batch::<NetworkInfo>(&calls).await.unwrap();
+        assert_eq!(infos.len(), 3);
+        assert!(infos.iter().all(|info| info.is_ok()));
+
+        let mut subscription = web3.qkc().subscribe::<Value>("newHeads", "0x00000001", None).await.unwrap();
+        assert_eq!(subscription.id(), "0xabc");
+        assert_eq!(subscription.next().await.unwrap().unwrap()["height"], 1);
+        assert_eq!(subscription.next().await.unwrap().unwrap()["height"], 2);
+
+        assert!(subscription.unsubscribe().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn subscription_drop_test() {
+        let (url, mut methods) = serve().await;
+        let web3 = QkcWeb3::connect_ws(&url, RetryPolicy::no_retries()).await.unwrap();
+
+        let subscription = web3.qkc().subscribe::<Value>("newHeads", "0x00000001", None).await.unwrap();
+        assert_eq!(methods.recv().await.unwrap(), "subscribe");
+        drop(subscription);
+        let method = tokio::time::timeout(std::time::Duration::from_secs(5), methods.recv()).await.expect("unsubscribe sent");
+        assert_eq!(method.unwrap(), "unsubscribe");
+
+        // cancelled once when unsubscribed explicitly
+        let subscription = web3.qkc().subscribe::<Value>("newHeads", "0x00000001", None).await.unwrap();
+        assert!(subscription.unsubscribe().await.unwrap());
+        web3.qkc().network_info().await.unwrap();
+        let received: Vec<_> = std::iter::from_fn(|| methods.try_recv().ok()).collect();
+        assert_eq!(received, ["subscribe", "unsubscribe", "networkInfo"]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Serialize, Deserialize, Serializer, Deserializer, de::Error};
+
+use super::{H160, Branch, ShardLayout, ShardError, keccak256};
+
+/// Error returned when parsing a QKC address
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid address {value:?}: {reason}")]
+pub struct AddressError {
+    pub value: String,
+    pub reason: &'static str
+}
+
+impl AddressError {
+    fn new(value: &str, reason: &'static str) -> Self {
+        Self {
+            value: value.to_string(),
+            reason
+        }
+    }
+}
+
+/// QKC address, a 20 byte coinbase followed by the 4 byte full shard key (chain id and shard id).
+/// Displayed in lowercase, the alternate form (`{:#}`) checksums the coinbase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct QkcAddress {
+    pub coinbase: H160,
+    full_shard_key: u32
+}
+
+impl QkcAddress {
+    pub fn from_parts(coinbase: H160, full_shard_key: u32) -> Self {
+        Self {
+            coinbase,
+            full_shard_key
+        }
+    }
+
+    pub fn coinbase(&self) -> String {
+        format!("0x{}", hex::encode(self.coinbase))
+    }
+
+    pub fn full_shard_key(&self) -> String {
+        format!("0x{:08x}", self.full_shard_key)
+    }
+
+    pub fn full_shard_key_u32(&self) -> u32 {
+        self.full_shard_key
+    }
+
+    pub fn chain_id(&self) -> u16 {
+        (self.full_shard_key >> 16) as u16
+    }
+
+    /// Low 16 bits of the full shard key, use [`QkcAddress::branch`] for the shard id within the network
+    pub fn shard_id(&self) -> u16 {
+        self.full_shard_key as u16
+    }
+
+    /// Shard of `layout` the address belongs to
+    pub fn branch(&self, layout: &ShardLayout) -> Result<Branch, ShardError> {
+        layout.branch(self.full_shard_key)
+    }
+
+    /// Address on chain 0 shard 0 from a 0x prefixed 20 byte coinbase
+    pub fn new_from_coinbase(coinbase: &str) -> Result<Self, AddressError> {
+        Self::new(coinbase, 0, 0)
+    }
+
+    pub fn new(coinbase: &str, chain_id: u16, shard_id: u16) -> Result<Self, AddressError> {
+        Ok(Self::from_parts(parse_coinbase(coinbase)?, (chain_id as u32) << 16 | shard_id as u32))
+    }
+
+    /// Address from its 0x prefixed 24 byte form, coinbase followed by the full shard key
+    pub fn new_full(address: &str) -> Result<Self, AddressError> {
+        let digits = strip_prefix(address)?;
+
+        if digits.len() != 48 {
+            return Err(AddressError::new(address, "expected 24 bytes"));
+        }
+
+        let (coinbase, full_shard_key) = digits.split_at_checked(40).ok_or_else(|| AddressError::new(address, "not hex"))?;
+        let full_shard_key = u32::from_str_radix(full_shard_key, 16).ok()
+            .filter(|_| full_shard_key.bytes().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| AddressError::new(address, "not hex"))?;
+
+        Ok(Self::from_parts(parse_coinbase_digits(address, coinbase)?, full_shard_key))
+    }
+}
+
+fn strip_prefix(value: &str) -> Result<&str, AddressError> {
+    value.strip_prefix("0x").ok_or_else(|| AddressError::new(value, "missing 0x prefix"))
+}
+
+/// EIP-55 mixed case encoding of `coinbase`, a hex digit is uppercase if the matching nibble of
+/// the keccak hash of the lowercase hex is 8 or more
+pub fn to_checksum(coinbase: &H160) -> String {
+    let hex = hex::encode(coinbase);
+    let hash = keccak256(hex.as_bytes());
+
+    let checksummed: String = hex.chars().enumerate()
+        .map(|(i, c)| match hash[i / 2] >> (4 * (1 - i % 2)) & 0xf {
+            8.. => c.to_ascii_uppercase(),
+            _ => c
+        })
+        .collect();
+
+    format!("0x{checksummed}")
+}
+
+/// Coinbase from 40 hex digits, mixed case digits must match the EIP-55 checksum
+fn parse_coinbase(coinbase: &str) -> Result<H160, AddressError> {
+    parse_coinbase_digits(coinbase, strip_prefix(coinbase)?)
+}
+
+/// `digits` are the coinbase part of `value`
+fn parse_coinbase_digits(value: &str, digits: &str) -> Result<H160, AddressError> {
+    if digits.len() != 40 {
+        return Err(AddressError::new(value, "expected 20 bytes"));
+    }
+
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(digits, &mut bytes).map_err(|_| AddressError::new(value, "not hex"))?;
+
+    let coinbase = H160(bytes);
+    let mixed_case = digits.bytes().any(|c| c.is_ascii_lowercase()) && digits.bytes().any(|c| c.is_ascii_uppercase());
+
+    if mixed_case && to_checksum(&coinbase)[2..] != *digits {
+        return Err(AddressError::new(value, "invalid checksum"));
+    }
+
+    Ok(coinbase)
+}
+
+impl FromStr for QkcAddress {
+    type Err = AddressError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        Self::new_full(address)
+    }
+}
+
+impl fmt::Display for QkcAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.alternate() {
+            true => write!(f, "{}{:08x}", to_checksum(&self.coinbase), self.full_shard_key),
+            false => write!(f, "{}{:08x}", self.coinbase(), self.full_shard_key)
+        }
+    }
+}
+
+impl Serialize for QkcAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for QkcAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn address_test() {
+        let address: QkcAddress = "0x13d041434910aD2C1893c6A77537B16Cb7b8Ef5b00030001".parse().unwrap();
+        assert_eq!(address.coinbase(), "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b");
+        assert_eq!(address.full_shard_key(), "0x00030001");
+        assert_eq!(address.chain_id(), 3);
+        assert_eq!(address.shard_id(), 1);
+        assert_eq!(address.to_string(), "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00030001");
+        assert_eq!(address, QkcAddress::new("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b", 3, 1).unwrap());
+
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, "\"0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00030001\"");
+        assert_eq!(serde_json::from_str::<QkcAddress>(&json).unwrap(), address);
+
+        let set: HashSet<_> = [address, QkcAddress::new_full("0x13d041434910ad2c1893c6a77537b16cb7b8ef5b00030001").unwrap()].into();
+        assert_eq!(set.len(), 1);
+
+        for invalid in ["", "0x", "13d041434910ad2c1893c6a77537b16cb7b8ef5b00030001", "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b0003000",
+            "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b0003000z", "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b000300010"] {
+            assert!(invalid.parse::<QkcAddress>().is_err(), "{invalid}");
+        }
+        assert!(QkcAddress::new_from_coinbase("0x13d041434910ad2c1893c6a77537b16cb7b8ef").is_err());
+        assert!(serde_json::from_str::<QkcAddress>("\"0x13d0\"").is_err());
+        assert_eq!(
+            "0x13d0".parse::<QkcAddress>().unwrap_err().to_string(),
+            "invalid address \"0x13d0\": expected 24 bytes"
+        );
+    }
+
+    #[test]
+    fn checksum_test() {
+        for checksummed in ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB", "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb"] {
+            let address = QkcAddress::new_from_coinbase(checksummed).unwrap();
+            assert_eq!(to_checksum(&address.coinbase), checksummed);
+            assert_eq!(address, QkcAddress::new_from_coinbase(&checksummed.to_lowercase()).unwrap());
+            assert_eq!(address, QkcAddress::new_from_coinbase(&checksummed.to_uppercase().replace("0X", "0x")).unwrap());
+        }
+
+        let address: QkcAddress = "0x13d041434910aD2C1893c6A77537B16Cb7b8Ef5b0003C66C".parse().unwrap();
+        assert_eq!(format!("{address:#}"), "0x13d041434910aD2C1893c6A77537B16Cb7b8Ef5b0003c66c");
+        assert_eq!(address.to_string(), "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b0003c66c");
+
+        let wrong_case = "0x13d041434910Ad2C1893c6A77537B16Cb7b8Ef5b";
+        assert_eq!(QkcAddress::new_from_coinbase(wrong_case).unwrap_err().reason, "invalid checksum");
+        assert!(format!("{wrong_case}00030001").parse::<QkcAddress>().is_err());
+    }
+}
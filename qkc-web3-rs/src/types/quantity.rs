@@ -0,0 +1,179 @@
+use std::{fmt, ops::Deref, str::FromStr};
+
+use serde::{Serialize, Deserialize, Serializer, Deserializer, de::Error};
+
+/// Error returned when parsing a hex quantity or hex data string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid hex value {value:?}: {reason}")]
+pub struct HexError {
+    pub value: String,
+    pub reason: &'static str
+}
+
+impl HexError {
+    fn new(value: &str, reason: &'static str) -> Self {
+        Self {
+            value: value.to_string(),
+            reason
+        }
+    }
+}
+
+fn strip_prefix(value: &str) -> Result<&str, HexError> {
+    value.strip_prefix("0x").ok_or_else(|| HexError::new(value, "missing 0x prefix"))
+}
+
+macro_rules! hex_quantity {
+    ($(#[$meta:meta])* $name:ident, $inner:ty) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub $inner);
+
+        impl FromStr for $name {
+            type Err = HexError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                let digits = strip_prefix(value)?;
+
+                if digits.is_empty() {
+                    return Err(HexError::new(value, "empty quantity"));
+                }
+                // from_str_radix also accepts a leading sign
+                if !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(HexError::new(value, "not hex"));
+                }
+
+                <$inner>::from_str_radix(digits, 16)
+                    .map(Self)
+                    .map_err(|_| HexError::new(value, concat!("not a hex ", stringify!($inner))))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{:#x}", self.0)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = $inner;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl From<$inner> for $name {
+            fn from(value: $inner) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+
+                value.parse().map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+hex_quantity!(
+    /// `u64` (de)serialized as 0x prefixed hex quantity, e.g. block heights
+    HexU64, u64
+);
+
+hex_quantity!(
+    /// `u128` (de)serialized as 0x prefixed hex quantity, e.g. difficulties
+    HexU128, u128
+);
+
+/// Arbitrary bytes (de)serialized as 0x prefixed hex data
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Bytes(pub Vec<u8>);
+
+impl FromStr for Bytes {
+    type Err = HexError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        hex::decode(strip_prefix(value)?)
+            .map(Self)
+            .map_err(|_| HexError::new(value, "not hex data"))
+    }
+}
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(&self.0))
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quantity_test() {
+        assert_eq!("0x0".parse::<HexU64>().unwrap(), HexU64(0));
+        assert_eq!("0xff".parse::<HexU128>().unwrap(), HexU128(255));
+        assert_eq!(HexU64(255).to_string(), "0xff");
+
+        assert!("0x".parse::<HexU64>().is_err());
+        assert!("ff".parse::<HexU64>().is_err());
+        assert!("0xzz".parse::<HexU64>().is_err());
+        assert_eq!("0x+1".parse::<HexU64>().unwrap_err().reason, "not hex");
+        assert!("0x-1".parse::<HexU128>().is_err());
+        assert!("0x1ffffffffffffffff".parse::<HexU64>().is_err());
+
+        let height: HexU64 = serde_json::from_str("\"0x10\"").unwrap();
+        assert_eq!(*height, 16);
+        assert_eq!(serde_json::to_string(&height).unwrap(), "\"0x10\"");
+        assert!(serde_json::from_str::<HexU64>("\"0x\"").is_err());
+
+        let data: Bytes = serde_json::from_str("\"0x0102\"").unwrap();
+        assert_eq!(&*data, &[1, 2]);
+        assert_eq!(data.to_string(), "0x0102");
+        assert!("0x1".parse::<Bytes>().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use super::{H160, QkcAddress};
+
+/// Error returned when a chain or shard does not exist in a [`ShardLayout`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ShardError {
+    #[error("shard size {0} of chain {1} is not a power of two")]
+    InvalidShardSize(u64, usize),
+
+    #[error("{0} chains do not fit in a full shard key")]
+    TooManyChains(usize),
+
+    #[error("chain {0} does not exist")]
+    UnknownChain(u16),
+
+    #[error("shard {1} does not exist on chain {0}")]
+    UnknownShard(u16, u16),
+
+    #[error("full shard id {0:#010x} is not a valid branch")]
+    InvalidBranch(u32)
+}
+
+/// A shard of the network, encoded like QuarkChain's `Branch`: chain id in the high 16 bits,
+/// then the shard size bit followed by the shard id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Branch(u32);
+
+impl Branch {
+    pub fn new(chain_id: u16, shard_size: u16, shard_id: u16) -> Result<Self, ShardError> {
+        if !shard_size.is_power_of_two() {
+            return Err(ShardError::InvalidShardSize(shard_size as u64, chain_id as usize));
+        }
+        if shard_id >= shard_size {
+            return Err(ShardError::UnknownShard(chain_id, shard_id));
+        }
+
+        Ok(Self((chain_id as u32) << 16 | shard_size as u32 | shard_id as u32))
+    }
+
+    /// Branch from a full shard id as returned by the node, e.g. `fullShardId` of a block
+    pub fn from_full_shard_id(full_shard_id: u32) -> Result<Self, ShardError> {
+        let low = full_shard_id as u16;
+
+        if low == 0 {
+            return Err(ShardError::InvalidBranch(full_shard_id));
+        }
+
+        let shard_size = 1 << (15 - low.leading_zeros());
+        Self::new((full_shard_id >> 16) as u16, shard_size, low ^ shard_size)
+    }
+
+    pub fn full_shard_id(&self) -> u32 {
+        self.0
+    }
+
+    pub fn chain_id(&self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub fn shard_size(&self) -> u16 {
+        1 << (15 - (self.0 as u16).leading_zeros())
+    }
+
+    pub fn shard_id(&self) -> u16 {
+        self.0 as u16 ^ self.shard_size()
+    }
+
+    /// Smallest full shard key that belongs to this branch
+    pub fn full_shard_key(&self) -> u32 {
+        (self.chain_id() as u32) << 16 | self.shard_id() as u32
+    }
+}
+
+impl fmt::Display for Branch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}", self.0)
+    }
+}
+
+/// Number of shards of every chain of a network, see [`NetworkInfo::shard_layout`](crate::qkc::NetworkInfo::shard_layout)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardLayout {
+    shard_sizes: Vec<u16>
+}
+
+impl ShardLayout {
+    /// Layout from the shard size of each chain, in chain id order
+    pub fn new(shard_sizes: &[u64]) -> Result<Self, ShardError> {
+        if shard_sizes.len() > u16::MAX as usize + 1 {
+            return Err(ShardError::TooManyChains(shard_sizes.len()));
+        }
+
+        let shard_sizes = shard_sizes.iter().enumerate()
+            .map(|(chain_id, &size)| match u16::try_from(size) {
+                Ok(size) if size.is_power_of_two() => Ok(size),
+                _ => Err(ShardError::InvalidShardSize(size, chain_id))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            shard_sizes
+        })
+    }
+
+    pub fn chain_size(&self) -> usize {
+        self.shard_sizes.len()
+    }
+
+    pub fn shard_size(&self, chain_id: u16) -> Result<u16, ShardError> {
+        self.shard_sizes.get(chain_id as usize).copied().ok_or(ShardError::UnknownChain(chain_id))
+    }
+
+    /// Branch a full shard key belongs to, the shard id is the key's low bits masked by the shard size
+    pub fn branch(&self, full_shard_key: u32) -> Result<Branch, ShardError> {
+        let chain_id = (full_shard_key >> 16) as u16;
+        let shard_size = self.shard_size(chain_id)?;
+
+        Branch::new(chain_id, shard_size, full_shard_key as u16 & (shard_size - 1))
+    }
+
+    /// Full shard key of a (chain, shard) pair
+    pub fn full_shard_key(&self, chain_id: u16, shard_id: u16) -> Result<u32, ShardError> {
+        Ok(Branch::new(chain_id, self.shard_size(chain_id)?, shard_id)?.full_shard_key())
+    }
+
+    /// Every shard of the network, by chain and then shard id
+    pub fn branches(&self) -> impl Iterator<Item = Branch> + '_ {
+        self.shard_sizes.iter().enumerate().flat_map(|(chain_id, &shard_size)| {
+            (0..shard_size).map(move |shard_id| Branch((chain_id as u32) << 16 | shard_size as u32 | shard_id as u32))
+        })
+    }
+
+    /// Addresses of `coinbase` on every shard of the network
+    pub fn addresses(&self, coinbase: H160) -> Vec<QkcAddress> {
+        self.branches().map(|branch| QkcAddress::from_parts(coinbase, branch.full_shard_key())).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shard_layout_test() {
+        let layout = ShardLayout::new(&[1, 1, 4]).unwrap();
+        assert_eq!(layout.chain_size(), 3);
+
+        let branch = layout.branch(0x0002_0007).unwrap();
+        assert_eq!(branch.full_shard_id(), 0x0002_0007);
+        assert_eq!((branch.chain_id(), branch.shard_size(), branch.shard_id()), (2, 4, 3));
+        assert_eq!(branch.full_shard_key(), 0x0002_0003);
+        assert_eq!(branch.to_string(), "0x00020007");
+        assert_eq!(Branch::from_full_shard_id(0x0002_0007).unwrap(), branch);
+
+        assert_eq!(layout.branch(0x0001_1234).unwrap().full_shard_id(), 0x0001_0001);
+        assert_eq!(layout.full_shard_key(2, 1).unwrap(), 0x0002_0001);
+        assert_eq!(layout.branch(0x0003_0000), Err(ShardError::UnknownChain(3)));
+        assert_eq!(layout.full_shard_key(2, 4), Err(ShardError::UnknownShard(2, 4)));
+        assert_eq!(Branch::from_full_shard_id(0x0002_0000), Err(ShardError::InvalidBranch(0x0002_0000)));
+
+        let branches = layout.branches().map(|b| b.full_shard_id()).collect::<Vec<_>>();
+        assert_eq!(branches, vec![0x0000_0001, 0x0001_0001, 0x0002_0004, 0x0002_0005, 0x0002_0006, 0x0002_0007]);
+
+        let addresses = layout.addresses(H160::repeat_byte(0x11));
+        assert_eq!(addresses.len(), 6);
+        assert_eq!(addresses[5].full_shard_key(), "0x00020003");
+
+        assert_eq!(ShardLayout::new(&[1, 3]), Err(ShardError::InvalidShardSize(3, 1)));
+        assert_eq!(ShardLayout::new(&[0]), Err(ShardError::InvalidShardSize(0, 0)));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use bip39::Mnemonic;
+use hmac::{Hmac, KeyInit, Mac};
+use k256::{Scalar, ecdsa::SigningKey, elliptic_curve::PrimeField};
+use sha2::Sha512;
+
+use crate::types::{QkcAddress, ShardLayout};
+use super::{Wallet, WalletError};
+
+/// Index bit of hardened children, written `'` in paths
+const HARDENED: u32 = 1 << 31;
+
+/// Default account path, coinbases match Ethereum wallets using the same mnemonic
+pub const DEFAULT_ACCOUNT_PATH: &str = "m/44'/60'/0'/0";
+
+/// BIP32 derivation path such as `m/44'/60'/0'/0/0`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Path extended by the non hardened child `index`
+    pub fn child(&self, index: u32) -> Result<Self, WalletError> {
+        if index >= HARDENED {
+            return Err(WalletError::Path(format!("{self}/{index}")));
+        }
+
+        let mut path = self.0.clone();
+        path.push(index);
+
+        Ok(Self(path))
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = WalletError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut parts = path.split('/');
+
+        if parts.next() != Some("m") {
+            return Err(WalletError::Path(path.to_string()));
+        }
+
+        parts.map(|part| {
+            let (index, hardened) = match part.strip_suffix('\'') {
+                Some(index) => (index, HARDENED),
+                None => (part, 0)
+            };
+
+            match index.parse::<u32>() {
+                Ok(index) if index < HARDENED => Ok(index | hardened),
+                _ => Err(WalletError::Path(path.to_string()))
+            }
+        })
+        .collect::<Result<_, _>>()
+        .map(Self)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+
+        for index in &self.0 {
+            match index & HARDENED {
+                0 => write!(f, "/{index}")?,
+                _ => write!(f, "/{}'", index ^ HARDENED)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// BIP32 extended private key
+#[derive(Clone)]
+struct ExtendedKey {
+    key: SigningKey,
+    chain_code: [u8; 32]
+}
+
+impl ExtendedKey {
+    fn master(seed: &[u8]) -> Result<Self, WalletError> {
+        Self::from_hmac(hmac(b"Bitcoin seed", &[seed]), None)
+    }
+
+    fn child(&self, index: u32) -> Result<Self, WalletError> {
+        let index_bytes = index.to_be_bytes();
+        let i = match index & HARDENED {
+            0 => hmac(&self.chain_code, &[self.key.verifying_key().to_sec1_point(true).as_bytes(), &index_bytes]),
+            _ => hmac(&self.chain_code, &[&[0], &self.key.to_bytes(), &index_bytes])
+        };
+
+        Self::from_hmac(i, Some(&self.key))
+    }
+
+    /// Key from the left half of `i` (added to the parent key if any) and chain code from the right half
+    fn from_hmac(i: [u8; 64], parent: Option<&SigningKey>) -> Result<Self, WalletError> {
+        let invalid = || WalletError::Key(k256::ecdsa::Error::new());
+
+        let tweak = Option::<Scalar>::from(Scalar::from_repr(<[u8; 32]>::try_from(&i[..32]).unwrap().into())).ok_or_else(invalid)?;
+        let scalar = match parent {
+            Some(parent) => tweak + parent.as_nonzero_scalar().as_ref(),
+            None => tweak
+        };
+
+        Ok(Self {
+            key: SigningKey::from_bytes(&scalar.to_repr())?,
+            chain_code: i[32..].try_into().unwrap()
+        })
+    }
+}
+
+fn hmac(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("hmac accepts any key length");
+    data.iter().for_each(|data| mac.update(data));
+
+    mac.finalize().into_bytes().into()
+}
+
+/// Hierarchical deterministic wallet deriving numbered accounts below an account path from a BIP39 seed
+#[derive(Clone)]
+pub struct HdWallet {
+    root: ExtendedKey,
+    path: DerivationPath,
+    account: ExtendedKey
+}
+
+impl HdWallet {
+    /// Wallet from a BIP39 mnemonic and optional passphrase, using [`DEFAULT_ACCOUNT_PATH`]
+    pub fn from_mnemonic(mnemonic: &str, passphrase: &str) -> Result<Self, WalletError> {
+        let mnemonic = Mnemonic::parse(mnemonic)?;
+
+        Self::from_seed(&mnemonic.to_seed(passphrase))
+    }
+
+    pub fn from_seed(seed: &[u8]) -> Result<Self, WalletError> {
+        let root = ExtendedKey::master(seed)?;
+
+        Self {
+            account: root.clone(),
+            root,
+            path: DerivationPath::default()
+        }
+        .with_path(DEFAULT_ACCOUNT_PATH.parse()?)
+    }
+
+    /// New random English mnemonic of `word_count` words (12, 15, 18, 21 or 24)
+    pub fn generate_mnemonic(word_count: usize) -> Result<String, WalletError> {
+        if !(12..=24).contains(&word_count) || !word_count.is_multiple_of(3) {
+            return Err(WalletError::Mnemonic(bip39::Error::BadWordCount(word_count)));
+        }
+
+        let entropy = rand::random::<[u8; 32]>();
+        Ok(Mnemonic::from_entropy(&entropy[..word_count / 3 * 4])?.to_string())
+    }
+
+    /// Derives accounts below `path` instead
+    pub fn with_path(mut self, path: DerivationPath) -> Result<Self, WalletError> {
+        self.account = derive(&self.root, &path)?;
+        self.path = path;
+
+        Ok(self)
+    }
+
+    pub fn path(&self) -> &DerivationPath {
+        &self.path
+    }
+
+    /// Key at `path`, from the root of the wallet
+    pub fn derive(&self, path: &DerivationPath) -> Result<Wallet, WalletError> {
+        Ok(Wallet::new(derive(&self.root, path)?.key))
+    }
+
+    /// Account `index` below the account path
+    pub fn wallet(&self, index: u32) -> Result<Wallet, WalletError> {
+        if index >= HARDENED {
+            return Err(WalletError::Path(format!("{}/{index}", self.path)));
+        }
+
+        Ok(Wallet::new(self.account.child(index)?.key))
+    }
+
+    /// Address of account `index` on the shard of `full_shard_key`
+    pub fn address(&self, index: u32, full_shard_key: u32) -> Result<QkcAddress, WalletError> {
+        Ok(self.wallet(index)?.address(full_shard_key))
+    }
+
+    /// Accounts `indices` below the account path
+    pub fn wallets(&self, indices: Range<u32>) -> Result<Vec<Wallet>, WalletError> {
+        indices.map(|index| self.wallet(index)).collect()
+    }
+
+    /// Addresses of accounts `indices` on every shard of `layout`, by account
+    pub fn addresses(&self, indices: Range<u32>, layout: &ShardLayout) -> Result<Vec<QkcAddress>, WalletError> {
+        Ok(self.wallets(indices)?.iter().flat_map(|wallet| layout.addresses(wallet.coinbase())).collect())
+    }
+}
+
+impl fmt::Debug for HdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdWallet").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+fn derive(root: &ExtendedKey, path: &DerivationPath) -> Result<ExtendedKey, WalletError> {
+    path.0.iter().try_fold(root.clone(), |key, index| key.child(*index))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn derivation_path_test() {
+        let path: DerivationPath = "m/44'/60'/0'/0".parse().unwrap();
+        assert_eq!(path.indices(), &[44 | HARDENED, 60 | HARDENED, HARDENED, 0]);
+        assert_eq!(path.child(3).unwrap().to_string(), "m/44'/60'/0'/0/3");
+        assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::default());
+
+        for invalid in ["", "44'/60'", "m/", "m/x", "m/2147483648", "m/1''"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{invalid}");
+        }
+    }
+
+    /// Test vector 1 of BIP32
+    #[test]
+    fn bip32_test() {
+        let wallet = HdWallet::from_seed(&hex::decode("000102030405060708090a0b0c0d0e0f").unwrap()).unwrap();
+
+        let key = wallet.derive(&"m/0'/1/2'/2/1000000000".parse().unwrap()).unwrap();
+        assert_eq!(hex::encode(key.to_bytes()), "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8");
+    }
+
+    #[test]
+    fn hd_wallet_test() {
+        let wallet = HdWallet::from_mnemonic(MNEMONIC, "").unwrap();
+        assert_eq!(format!("{:?}", wallet.wallet(0).unwrap().coinbase()), "0x9858effd232b4033e47d90003d41ec34ecaeda94");
+        assert_eq!(wallet.address(0, 0x00010000).unwrap().to_string(), "0x9858effd232b4033e47d90003d41ec34ecaeda9400010000");
+        assert_eq!(wallet.derive(&"m/44'/60'/0'/0/1".parse().unwrap()).unwrap().coinbase(), wallet.wallet(1).unwrap().coinbase());
+
+        let layout = ShardLayout::new(&[1, 1]).unwrap();
+        let addresses = wallet.addresses(0..3, &layout).unwrap();
+        assert_eq!(addresses.len(), 6);
+        assert_eq!(addresses[1], wallet.address(0, 0x00010000).unwrap());
+
+        let other = wallet.clone().with_path("m/44'/60'/1'/0".parse().unwrap()).unwrap();
+        assert_ne!(other.wallet(0).unwrap().coinbase(), wallet.wallet(0).unwrap().coinbase());
+        assert_ne!(HdWallet::from_mnemonic(MNEMONIC, "passphrase").unwrap().wallet(0).unwrap().coinbase(), wallet.wallet(0).unwrap().coinbase());
+
+        assert!(HdWallet::from_mnemonic("abandon abandon abandon", "").is_err());
+        assert!(HdWallet::from_mnemonic(&MNEMONIC.replace("about", "abandon"), "").is_err());
+
+        let mnemonic = HdWallet::generate_mnemonic(24).unwrap();
+        assert_eq!(mnemonic.split(' ').count(), 24);
+        assert!(HdWallet::from_mnemonic(&mnemonic, "").is_ok());
+        assert!(HdWallet::generate_mnemonic(13).is_err());
+    }
+}
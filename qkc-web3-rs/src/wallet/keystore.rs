@@ -0,0 +1,258 @@
+use std::path::Path;
+
+use aes::{Aes128, cipher::{KeyIvInit, StreamCipher}};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+
+use crate::types::keccak256;
+use super::{Wallet, WalletError};
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+const CIPHER: &str = "aes-128-ctr";
+const PRF: &str = "hmac-sha256";
+const DKLEN: u32 = 32;
+
+/// Key derivation function of a keystore, with its parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+pub enum Kdf {
+    Scrypt {
+        dklen: u32,
+        n: u32,
+        r: u32,
+        p: u32,
+        #[serde(with = "hex")]
+        salt: Vec<u8>
+    },
+    Pbkdf2 {
+        dklen: u32,
+        c: u32,
+        prf: String,
+        #[serde(with = "hex")]
+        salt: Vec<u8>
+    }
+}
+
+impl Kdf {
+    /// scrypt with cost `n` (a power of two), block size `r` and parallelism `p` and a random salt
+    pub fn scrypt(n: u32, r: u32, p: u32) -> Self {
+        Kdf::Scrypt {
+            dklen: DKLEN,
+            n,
+            r,
+            p,
+            salt: rand::random::<[u8; 32]>().to_vec()
+        }
+    }
+
+    /// PBKDF2-HMAC-SHA256 with `c` iterations and a random salt
+    pub fn pbkdf2(c: u32) -> Self {
+        Kdf::Pbkdf2 {
+            dklen: DKLEN,
+            c,
+            prf: PRF.to_string(),
+            salt: rand::random::<[u8; 32]>().to_vec()
+        }
+    }
+
+    fn derive(&self, password: &str) -> Result<Vec<u8>, WalletError> {
+        match self {
+            Kdf::Scrypt { dklen, n, r, p, salt } => {
+                if !n.is_power_of_two() {
+                    return Err(WalletError::Unsupported(format!("scrypt n {n}")));
+                }
+
+                let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p)
+                    .map_err(|e| WalletError::Unsupported(format!("scrypt parameters: {e}")))?;
+                let mut key = vec![0; check_dklen(*dklen)?];
+                scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+                    .map_err(|e| WalletError::Unsupported(format!("scrypt dklen: {e}")))?;
+
+                Ok(key)
+            },
+            Kdf::Pbkdf2 { dklen, c, prf, salt } => {
+                if prf != PRF {
+                    return Err(WalletError::Unsupported(format!("prf {prf}")));
+                }
+
+                let mut key = vec![0; check_dklen(*dklen)?];
+                pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, *c, &mut key);
+
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// geth's standard scrypt parameters
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::scrypt(1 << 18, 8, 1)
+    }
+}
+
+/// The first 16 bytes of the derived key encrypt the private key, the next 16 authenticate the ciphertext
+fn check_dklen(dklen: u32) -> Result<usize, WalletError> {
+    match dklen {
+        32..=1024 => Ok(dklen as usize),
+        _ => Err(WalletError::Unsupported(format!("dklen {dklen}")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CipherParams {
+    #[serde(with = "hex")]
+    pub iv: Vec<u8>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Crypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    #[serde(with = "hex")]
+    pub ciphertext: Vec<u8>,
+    #[serde(flatten)]
+    pub kdf: Kdf,
+    #[serde(with = "hex")]
+    pub mac: Vec<u8>
+}
+
+/// Ethereum v3 JSON keystore, a private key encrypted with AES-128-CTR under a password derived key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub id: String,
+    /// Coinbase of the key, hex without 0x prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(alias = "Crypto")]
+    pub crypto: Crypto
+}
+
+impl Keystore {
+    pub fn encrypt(wallet: &Wallet, password: &str, kdf: Kdf) -> Result<Self, WalletError> {
+        let key = kdf.derive(password)?;
+        let iv = rand::random::<[u8; 16]>();
+
+        let mut ciphertext = wallet.to_bytes().to_vec();
+        cipher(&key, &iv).apply_keystream(&mut ciphertext);
+
+        Ok(Self {
+            version: 3,
+            id: uuid(),
+            address: Some(hex::encode(wallet.coinbase())),
+            crypto: Crypto {
+                cipher: CIPHER.to_string(),
+                cipherparams: CipherParams {
+                    iv: iv.to_vec()
+                },
+                mac: mac(&key, &ciphertext),
+                ciphertext,
+                kdf
+            }
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<Wallet, WalletError> {
+        if self.version != 3 {
+            return Err(WalletError::Unsupported(format!("version {}", self.version)));
+        }
+        if self.crypto.cipher != CIPHER {
+            return Err(WalletError::Unsupported(format!("cipher {}", self.crypto.cipher)));
+        }
+        if self.crypto.cipherparams.iv.len() != 16 {
+            return Err(WalletError::Unsupported(format!("iv length {}", self.crypto.cipherparams.iv.len())));
+        }
+
+        let key = self.crypto.kdf.derive(password)?;
+        if mac(&key, &self.crypto.ciphertext) != self.crypto.mac {
+            return Err(WalletError::Mac);
+        }
+
+        let mut private_key = self.crypto.ciphertext.clone();
+        cipher(&key, &self.crypto.cipherparams.iv).apply_keystream(&mut private_key);
+        let wallet = Wallet::from_bytes(&private_key)?;
+
+        match &self.address {
+            Some(address) if !address.trim_start_matches("0x").eq_ignore_ascii_case(&hex::encode(wallet.coinbase())) => {
+                Err(WalletError::AddressMismatch {
+                    expected: address.clone(),
+                    actual: wallet.coinbase()
+                })
+            },
+            _ => Ok(wallet)
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WalletError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WalletError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+fn cipher(key: &[u8], iv: &[u8]) -> Aes128Ctr {
+    Aes128Ctr::new_from_slices(&key[..16], iv).expect("16 byte key and iv")
+}
+
+fn mac(key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    keccak256(&[&key[16..32], ciphertext].concat()).as_bytes().to_vec()
+}
+
+/// Random (version 4) uuid
+fn uuid() -> String {
+    let mut bytes = rand::random::<[u8; 16]>();
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    /// Test vector of the Web3 Secret Storage Definition
+    const PBKDF2_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": {"iv": "6087dab2f9fdbbfaddc31a909735c1e6"},
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {"c": 262144, "dklen": 32, "prf": "hmac-sha256", "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"},
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    #[test]
+    fn keystore_test() {
+        let keystore: Keystore = serde_json::from_str(PBKDF2_KEYSTORE).unwrap();
+        assert_eq!(hex::encode(keystore.decrypt("testpassword").unwrap().to_bytes()), PRIVATE_KEY);
+        assert!(matches!(keystore.decrypt("wrong"), Err(WalletError::Mac)));
+
+        let wallet = Wallet::from_hex(PRIVATE_KEY).unwrap();
+        let keystore = Keystore::encrypt(&wallet, "password", Kdf::scrypt(1 << 10, 8, 1)).unwrap();
+        let json = serde_json::to_value(&keystore).unwrap();
+        assert_eq!(json["crypto"]["kdf"], "scrypt");
+        assert_eq!(json["crypto"]["kdfparams"]["n"], 1024);
+        assert_eq!(json["address"], hex::encode(wallet.coinbase()));
+
+        let keystore: Keystore = serde_json::from_value(json).unwrap();
+        assert_eq!(keystore.decrypt("password").unwrap().to_bytes(), wallet.to_bytes());
+        assert!(matches!(keystore.decrypt("wrong"), Err(WalletError::Mac)));
+
+        let mut other = keystore.clone();
+        other.address = Some(hex::encode([0u8; 20]));
+        assert!(matches!(other.decrypt("password"), Err(WalletError::AddressMismatch { .. })));
+    }
+}
@@ -0,0 +1,141 @@
+//! Local signing keys, from raw keys, keystore files or a mnemonic, and the addresses derived from them
+
+use std::path::Path;
+
+use k256::ecdsa::SigningKey;
+
+use crate::types::{EvmTransaction, SignedTransaction, SigningError, QkcAddress, public_key_address, H160};
+
+mod hd;
+mod keystore;
+
+pub use hd::{HdWallet, DerivationPath, DEFAULT_ACCOUNT_PATH};
+pub use keystore::{Keystore, Kdf, Crypto, CipherParams};
+
+/// Error returned when loading, decrypting or saving a key
+#[derive(Debug, thiserror::Error)]
+pub enum WalletError {
+    #[error("invalid private key: {0}")]
+    Key(#[from] k256::ecdsa::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid keystore: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("unsupported keystore: {0}")]
+    Unsupported(String),
+
+    #[error("invalid mnemonic: {0}")]
+    Mnemonic(#[from] bip39::Error),
+
+    #[error("invalid derivation path {0}")]
+    Path(String),
+
+    #[error("wrong password or corrupted keystore")]
+    Mac,
+
+    #[error("keystore address {expected} does not match its key {actual:?}")]
+    AddressMismatch {
+        expected: String,
+        actual: H160
+    }
+}
+
+/// secp256k1 private key owning the same coinbase on every shard
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    key: SigningKey,
+    coinbase: H160
+}
+
+impl Wallet {
+    pub fn new(key: SigningKey) -> Self {
+        let coinbase = public_key_address(key.verifying_key());
+
+        Self {
+            key,
+            coinbase
+        }
+    }
+
+    /// Wallet from a 32 byte private key
+    pub fn from_bytes(key: &[u8]) -> Result<Self, WalletError> {
+        Ok(Self::new(SigningKey::from_slice(key)?))
+    }
+
+    /// Wallet from a hex private key, with or without 0x prefix
+    pub fn from_hex(key: &str) -> Result<Self, WalletError> {
+        let key = hex::decode(key.trim_start_matches("0x")).map_err(|_| WalletError::Key(k256::ecdsa::Error::new()))?;
+
+        Self::from_bytes(&key)
+    }
+
+    /// Wallet with a new random key
+    pub fn random() -> Self {
+        loop {
+            if let Ok(wallet) = Self::from_bytes(&rand::random::<[u8; 32]>()) {
+                return wallet;
+            }
+        }
+    }
+
+    /// Decrypts a v3 keystore file
+    pub fn load_keystore(path: impl AsRef<Path>, password: &str) -> Result<Self, WalletError> {
+        Keystore::load(path)?.decrypt(password)
+    }
+
+    /// Encrypts the key with the default scrypt parameters into a v3 keystore file
+    pub fn save_keystore(&self, path: impl AsRef<Path>, password: &str) -> Result<Keystore, WalletError> {
+        let keystore = Keystore::encrypt(self, password, Kdf::default())?;
+        keystore.save(path)?;
+
+        Ok(keystore)
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.key
+    }
+
+    /// Private key bytes
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.key.to_bytes().into()
+    }
+
+    pub fn coinbase(&self) -> H160 {
+        self.coinbase
+    }
+
+    /// Address of the key on the shard of `full_shard_key`
+    pub fn address(&self, full_shard_key: u32) -> QkcAddress {
+        QkcAddress::from_parts(self.coinbase, full_shard_key)
+    }
+
+    pub fn sign_transaction(&self, tx: EvmTransaction) -> Result<SignedTransaction, SigningError> {
+        tx.sign(&self.key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wallet_test() {
+        let wallet = Wallet::from_hex("0x4646464646464646464646464646464646464646464646464646464646464646").unwrap();
+        assert_eq!(format!("{:?}", wallet.coinbase()), "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+        assert_eq!(wallet.address(0x00010000).to_string(), "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f00010000");
+        assert_eq!(wallet.to_bytes(), [0x46; 32]);
+
+        let tx = EvmTransaction {
+            from_full_shard_key: 0x00010000,
+            ..Default::default()
+        };
+        assert_eq!(wallet.sign_transaction(tx).unwrap().sender().unwrap(), wallet.coinbase());
+
+        assert!(Wallet::from_bytes(&[0; 32]).is_err());
+        assert!(Wallet::from_hex("0xzz").is_err());
+        assert_ne!(Wallet::random().coinbase(), Wallet::random().coinbase());
+    }
+}
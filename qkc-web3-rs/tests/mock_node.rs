@@ -0,0 +1,185 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use qkc_mock_node::{MockChain, MockNode, MockShard, MockStakes};
+use qkc_web3_rs::{QkcWeb3, RetryPolicy, qkc::{Block, BlockEvent, Log, LogFilter}, transport::HttpTransport, types::{QkcAddress, H256}};
+
+const MINER: &str = "0x13d041434910ad2c1893c6a77537b16cb7b8ef5b";
+const OTHER: &str = "0xf0c9a075c4386ab8f08cf4529fdf77f6d2748d02";
+
+async fn node() -> (MockNode, QkcWeb3) {
+    let mut chain = MockChain {
+        root: MockShard::new(1000, 5_000_000, vec![MINER.into(), OTHER.into(), OTHER.into(), OTHER.into()]),
+        ..Default::default()
+    };
+    *chain.shard(1) = MockShard {
+        posw_mineable_blocks: Some(128),
+        ..MockShard::new(300, 1_000_000, vec![MINER.into(), OTHER.into()])
+    };
+
+    let account = chain.account(MINER);
+    account.balance = 1_000_000e18 as u128;
+    account.nonce = 7;
+    account.stakes = Some(MockStakes {
+        amount: 2_000_000e18 as u128,
+        signer: Some(OTHER.into()),
+        ..Default::default()
+    });
+
+    let node = MockNode::start(chain).await.unwrap();
+    let web3 = QkcWeb3::with_policy(node.url(), RetryPolicy::no_retries());
+
+    (node, web3)
+}
+
+#[tokio::test]
+async fn account_test() {
+    let (_node, web3) = node().await;
+    let address = QkcAddress::new(MINER, 1, 0).unwrap();
+
+    assert_eq!(*web3.qkc().network_info().await.unwrap().network_id, 1);
+    assert_eq!(web3.qkc().get_transaction_count(&address).await.unwrap(), 7);
+
+    let layout = web3.qkc().shard_layout().await.unwrap();
+    assert_eq!(layout.chain_size(), 8);
+    assert_eq!(address.branch(&layout).unwrap().full_shard_id(), 0x0001_0001);
+    assert_eq!(web3.qkc().get_full_shard_ids().await.unwrap(), layout.branches().collect::<Vec<_>>());
+
+    let balances = web3.qkc().get_balances(&address).await.unwrap();
+    assert_eq!(balances.balances[0].balance, (1_000_000e18 as u128).into());
+
+    let posw = web3.qkc().posw_info(&address).await.unwrap().unwrap();
+    assert_eq!(posw.mined_blocks, 128);
+    assert_eq!(posw.mineable_blocks, 128);
+
+    let stakes = web3.qkc().get_root_locked_stakes(&address).await.unwrap();
+    assert_eq!(stakes.locked_amount(), (2_000_000e18 as u128).into());
+    assert_eq!(format!("{:?}", stakes.signer()), OTHER);
+    assert_eq!(web3.qkc().get_root_posw_stake(&address).await.unwrap(), 2_000_000e18 as u128);
+}
+
+#[tokio::test]
+async fn block_test() {
+    let (node, web3) = node().await;
+    let address = QkcAddress::new(MINER, 1, 0).unwrap();
+
+    let root = web3.qkc().get_root_block_by_height(Block::Latest, false).await.unwrap();
+    assert_eq!(*root.height, 1000);
+    assert_eq!(root.miner.coinbase(), MINER);
+    assert!(root.posw_info.is_none());
+
+    let blocks = web3.qkc().get_minor_blocks_by_height(&address.full_shard_key(), &[10, 11, 12], false, true).await.unwrap();
+    assert_eq!(blocks.iter().map(|b| *b.height).collect::<Vec<_>>(), vec![10, 11, 12]);
+    assert_eq!(blocks[1].hash_prev_minor_block, blocks[0].hash);
+    assert!(blocks.iter().all(|b| b.posw_info.is_some()));
+
+    assert_eq!(web3.qkc().get_blocks_mined_in_recent_256(address).await.unwrap(), 128);
+
+    node.chain().shard(1).advance(2);
+    let latest = web3.qkc().get_minor_block_by_height(address.full_shard_key(), Block::Latest, false, false).await.unwrap();
+    assert_eq!(*latest.height, 302);
+}
+
+#[tokio::test]
+async fn endpoint_health_test() {
+    let (behind, _) = node().await;
+    let (ahead, _) = node().await;
+    ahead.chain().shard(1).advance(5);
+
+    let transport = HttpTransport::new(vec![behind.url(), ahead.url()], Duration::from_secs(5)).with_shard(0x0001_0000);
+    let web3 = QkcWeb3::with_transport(Arc::new(transport.clone()), RetryPolicy::no_retries());
+
+    // the first request triggers a health check in the background
+    web3.qkc().network_info().await.unwrap();
+    for _ in 0..100 {
+        if transport.endpoints().health(&ahead.url()).unwrap().minor_height.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let (behind, ahead) = (transport.endpoints().health(&behind.url()).unwrap(), transport.endpoints().health(&ahead.url()).unwrap());
+    assert_eq!((behind.root_height, behind.minor_height), (Some(1000), Some(300)));
+    assert_eq!((ahead.root_height, ahead.minor_height), (Some(1000), Some(305)));
+
+    let latest = web3.qkc().get_minor_block_by_height("0x00010000".into(), Block::Latest, false, false).await.unwrap();
+    assert_eq!(*latest.height, 305);
+}
+
+#[tokio::test]
+async fn block_stream_test() {
+    let (node, web3) = node().await;
+    let stream = web3.qkc().root_block_stream(Duration::from_millis(10));
+    futures::pin_mut!(stream);
+
+    let BlockEvent::Block(first) = stream.next().await.unwrap().unwrap() else { panic!("expected block") };
+    assert_eq!(*first.height, 1000);
+
+    node.chain().root.advance(3);
+    for height in 1001..=1003 {
+        let BlockEvent::Block(block) = stream.next().await.unwrap().unwrap() else { panic!("expected block") };
+        assert_eq!(*block.height, height);
+    }
+
+    {
+        let mut chain = node.chain();
+        chain.root.reorg(1001);
+        chain.root.advance(1);
+    }
+    assert!(matches!(stream.next().await.unwrap().unwrap(), BlockEvent::Reorg { height: 1001, depth: 2 }));
+    for height in 1002..=1004 {
+        let BlockEvent::Block(block) = stream.next().await.unwrap().unwrap() else { panic!("expected block") };
+        assert_eq!(*block.height, height);
+    }
+}
+
+#[tokio::test]
+async fn log_poller_test() {
+    let (node, web3) = node().await;
+    let mut poller = web3.qkc().log_poller(LogFilter::new().from_block(298), "0x00010000");
+    let heights = |logs: &[Log]| logs.iter().map(|log| (*log.block_height, log.removed)).collect::<Vec<_>>();
+
+    assert_eq!(heights(&poller.poll(web3.qkc()).await.unwrap()), [(298, false), (299, false), (300, false)]);
+    assert!(poller.poll(web3.qkc()).await.unwrap().is_empty());
+
+    node.chain().shard(1).advance(2);
+    let logs = poller.poll(web3.qkc()).await.unwrap();
+    assert_eq!(heights(&logs), [(301, false), (302, false)]);
+    let orphaned = logs[1].block_hash;
+
+    // the logs of the replaced blocks are retracted before the logs of the new fork
+    {
+        let mut chain = node.chain();
+        chain.shard(1).reorg(300);
+        chain.shard(1).advance(1);
+    }
+    let logs = poller.poll(web3.qkc()).await.unwrap();
+    assert_eq!(heights(&logs), [(301, true), (302, true), (301, false), (302, false), (303, false)]);
+    assert_eq!(logs[1].block_hash, orphaned);
+    assert_ne!(logs[3].block_hash, orphaned);
+    assert_eq!(poller.next_height(), Some(304));
+}
+
+#[tokio::test]
+async fn work_test() {
+    let (node, web3) = node().await;
+    let address = QkcAddress::new(MINER, 1, 0).unwrap();
+    let full_shard_key = address.full_shard_key();
+
+    let work = web3.qkc().get_work(Some(&full_shard_key), Some(address.coinbase)).await.unwrap();
+    assert_eq!(work.height, 301);
+    assert_eq!(work.difficulty, 1_000_000);
+    assert_eq!(work.effective_difficulty(), 50_000);
+
+    let root = web3.qkc().get_work(None, None).await.unwrap();
+    assert_eq!((root.height, root.posw_divider), (1001, None));
+    assert_ne!(root.header_hash, work.header_hash);
+
+    assert!(!web3.qkc().submit_work(Some(&full_shard_key), root.header_hash, 1, H256::zero(), None).await.unwrap());
+    assert!(web3.qkc().submit_work(Some(&full_shard_key), work.header_hash, 1, H256::zero(), None).await.unwrap());
+    assert_eq!(node.chain().shard(1).latest(), 301);
+
+    let next = web3.qkc().get_work(Some(&full_shard_key), None).await.unwrap();
+    assert_eq!(next.height, 302);
+    assert!(!web3.qkc().submit_work(Some(&full_shard_key), work.header_hash, 1, H256::zero(), None).await.unwrap());
+}